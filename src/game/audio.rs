@@ -3,6 +3,15 @@ use sdl2::audio::{AudioCallback, AudioSpecDesired, AudioDevice};
 use synth::Controller;
 use synth::effect::{SweepEffect, RandomEffect};
 
+/// A sound that items ask for when something happens to them
+#[derive(Copy, Clone, PartialEq)]
+pub enum Sound {
+    ItemGet,
+    Nothing,
+    KeyGet,
+    Checkpoint
+}
+
 pub struct Audio<'sdl> {
     _subsystem: sdl2::Subsystem<'sdl>,
     device: AudioDevice<MyCallback>
@@ -43,6 +52,15 @@ impl<'sdl> Audio<'sdl> {
 
     // TODO: Don't lock the audio thread, enqueue instead
 
+    pub fn play(&mut self, sound: Sound) {
+        match sound {
+            Sound::ItemGet => self.item_get(),
+            Sound::Nothing => self.nothing(),
+            Sound::KeyGet => self.key_get(),
+            Sound::Checkpoint => self.checkpoint()
+        }
+    }
+
     pub fn jump(&mut self) {
         let mut lock = self.device.lock();
         let mut controller = &mut ((*lock).controller);
@@ -213,6 +231,14 @@ impl<'sdl> Audio<'sdl> {
         self.explode();
     }
 
+    pub fn hurt(&mut self) {
+        self.nothing();
+    }
+
+    pub fn checkpoint(&mut self) {
+        self.item_get();
+    }
+
     pub fn start_drilling(&mut self) {
         let mut lock = self.device.lock();
         let mut controller = &mut ((*lock).controller);
@@ -0,0 +1,81 @@
+use std::path::{Path, PathBuf};
+
+/// An ordered list of level files, played one after the other
+pub struct Campaign {
+    levels: Vec<PathBuf>,
+    current: usize
+}
+
+impl Campaign {
+    /// A campaign that consists of only one level
+    pub fn single<P: AsRef<Path>>(path: P) -> Campaign {
+        Campaign {
+            levels: vec![path.as_ref().to_path_buf()],
+            current: 0
+        }
+    }
+
+    /// Loads a campaign file.
+    ///
+    /// Each line is the path to a level, relative to the campaign file.
+    /// Blank lines and lines starting with `#` are ignored.
+    pub fn load_from_path<P: AsRef<Path>>(path: P) -> Result<Campaign, String> {
+        use std::fs::File;
+        use std::io::Read;
+
+        let path = path.as_ref();
+
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) => return Err(format!("Could not open {}: {}", path.display(), e))
+        };
+
+        let mut data = String::new();
+        match file.read_to_string(&mut data) {
+            Ok(_) => (),
+            Err(e) => return Err(format!("Could not read {}: {}", path.display(), e))
+        };
+
+        let base = path.parent().unwrap_or(Path::new(""));
+
+        let levels: Vec<PathBuf> = data.lines().map(|line| line.trim()).filter(|line| {
+            !line.is_empty() && !line.starts_with("#")
+        }).map(|line| base.join(line)).collect();
+
+        if levels.is_empty() {
+            return Err(format!("{} doesn't list any levels", path.display()));
+        }
+
+        Ok(Campaign {
+            levels: levels,
+            current: 0
+        })
+    }
+
+    pub fn current_level(&self) -> &Path {
+        &self.levels[self.current]
+    }
+
+    /// 0-based
+    pub fn current_index(&self) -> usize { self.current }
+
+    pub fn is_last_level(&self) -> bool {
+        self.current + 1 >= self.levels.len()
+    }
+
+    /// Goes back to the first level
+    pub fn restart(&mut self) {
+        self.current = 0;
+    }
+
+    /// Moves on to the next level.
+    /// Returns false if there are no more levels.
+    pub fn advance(&mut self) -> bool {
+        if self.is_last_level() {
+            false
+        } else {
+            self.current += 1;
+            true
+        }
+    }
+}
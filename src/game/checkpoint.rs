@@ -0,0 +1,77 @@
+use super::inventory::{Inventory, ItemTypes, KEY_ITEM};
+use super::items::{DynamicItems, Item};
+use super::level::{CheckpointRestore, Level, LevelState, Tile};
+use super::logic::Logic;
+use super::player::Player;
+use super::rect::Point;
+
+/// The parts of the world that the level's `checkpoint_restore` puts back
+enum Saved {
+    Nothing,
+    Doors {
+        doors: Vec<(u32, u32, Tile)>,
+        /// So the keys for doors opened since can be found again
+        door_items: Vec<(usize, Box<Item>)>,
+        keys: u32
+    },
+    Everything {
+        level: LevelState,
+        items: DynamicItems,
+        logic: Logic,
+        inventory: Inventory
+    }
+}
+
+/// The last checkpoint the player reached, and what the world looked like then
+pub struct ReachedCheckpoint {
+    /// Where the player comes back after dying
+    pub respawn: Point<f32>,
+    saved: Saved
+}
+
+impl ReachedCheckpoint {
+    pub fn new(respawn: Point<f32>, level: &Level, items: &DynamicItems, logic: &Logic, player: &Player, item_types: &ItemTypes) -> ReachedCheckpoint {
+        let saved = match level.checkpoint_restore {
+            CheckpointRestore::Nothing => Saved::Nothing,
+            CheckpointRestore::Doors => Saved::Doors {
+                doors: level.get_tiles().doors(),
+                door_items: items.save_door_items(),
+                keys: match item_types.find(KEY_ITEM) {
+                    Some(key) => player.inventory.count(key),
+                    None => 0
+                }
+            },
+            CheckpointRestore::Everything => Saved::Everything {
+                level: level.save_state(),
+                items: items.clone(),
+                logic: logic.clone(),
+                inventory: player.inventory.clone()
+            }
+        };
+
+        ReachedCheckpoint {
+            respawn: respawn,
+            saved: saved
+        }
+    }
+
+    /// Puts the world back the way it was when the checkpoint was reached
+    pub fn restore(&self, level: &mut Level, items: &mut DynamicItems, logic: &mut Logic, player: &mut Player, item_types: &ItemTypes) {
+        match self.saved {
+            Saved::Nothing => (),
+            Saved::Doors { ref doors, ref door_items, keys } => {
+                level.get_tiles_mut().relock_doors(doors);
+                items.restore_door_items(door_items);
+                if let Some(key) = item_types.find(KEY_ITEM) {
+                    player.inventory.set_count(item_types, key, keys);
+                }
+            },
+            Saved::Everything { level: ref saved_level, items: ref saved_items, logic: ref saved_logic, ref inventory } => {
+                level.restore_state(saved_level);
+                *items = saved_items.clone();
+                *logic = saved_logic.clone();
+                player.inventory = inventory.clone();
+            }
+        }
+    }
+}
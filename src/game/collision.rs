@@ -1,27 +1,296 @@
 use super::rect::Rect;
+use super::wrapping::Screen;
 
-pub fn test_rect_point(a: &Rect<f32>, b: (f32, f32)) -> bool {
-    let (ax1, ay1, ax2, ay2) = a.ltrb();
+// The screen wraps around, so every test here treats both axes as circles.
+// A rect that sticks out past the right edge carries on at x=0, and likewise for the bottom edge.
+
+/// Wraps `value` into [0, length)
+fn wrap(value: f32, length: f32) -> f32 {
+    let r = value % length;
+    if r < 0.0 {
+        // Adding a tiny negative number to `length` can round to `length` itself
+        let r = r + length;
+        if r >= length { 0.0 } else { r }
+    } else {
+        r
+    }
+}
+
+/// Whether [a, a + a_len) and [b, b + b_len) overlap on a circle `length` around.
+/// Two intervals overlap when either one starts inside the other.
+fn test_intervals(a: f32, a_len: f32, b: f32, b_len: f32, length: f32) -> bool {
+    wrap(b - a, length) < a_len || wrap(a - b, length) < b_len
+}
+
+pub fn test_rect_point(screen: &Screen, a: &Rect<f32>, b: (f32, f32)) -> bool {
+    let (ax, ay) = a.left_top().xy();
+    let (aw, ah) = a.size();
     let (bx, by) = b;
 
-    assert!(ax1 >= 0.0 && ay1 >= 0.0 && ax2 >= ax1 && ay2 >= ay1);
-    assert!(bx >= 0.0 && by >= 0.0);
+    test_intervals(ax, aw, bx, 0.0, screen.width) && test_intervals(ay, ah, by, 0.0, screen.height)
+}
+
+pub fn test_rects(screen: &Screen, a: &Rect<f32>, b: &Rect<f32>) -> bool {
+    let (ax, ay) = a.left_top().xy();
+    let (aw, ah) = a.size();
+    let (bx, by) = b.left_top().xy();
+    let (bw, bh) = b.size();
 
-    (bx >= ax1 && bx < ax2) && (by >= ay1 && by < ay2)
+    test_intervals(ax, aw, bx, bw, screen.width) && test_intervals(ay, ah, by, bh, screen.height)
 }
 
-pub fn test_rects(a: &Rect<f32>, b: &Rect<f32>) -> bool {
-    let (ax1, ay1, ax2, ay2) = a.ltrb();
-    let (bx1, by1, bx2, by2) = b.ltrb();
+/// Whether the vertical line at `x` passes through the rect
+pub fn test_rect_vert_line(screen: &Screen, a: &Rect<f32>, x: f32) -> bool {
+    test_rect_point(screen, a, (x, a.y()))
+}
+
+/// When an interval of length `a_len` that starts at 0 and moves by `d` overlaps [b, b + b_len),
+/// as a range of fractions of `d`
+fn sweep_interval(a_len: f32, d: f32, b: f32, b_len: f32) -> Option<(f32, f32)> {
+    use std::f32::{INFINITY, NEG_INFINITY};
 
-    test_rect_point(a, (bx1, by1)) || test_rect_point(a, (bx2, by1)) ||
-    test_rect_point(a, (bx1, by2)) || test_rect_point(a, (bx2, by2)) ||
-    test_rect_point(b, (ax1, ay1)) || test_rect_point(b, (ax2, ay1)) ||
-    test_rect_point(b, (ax1, ay2)) || test_rect_point(b, (ax2, ay2))
+    // The moving interval overlaps while its start is strictly between these
+    let (low, high) = (b - a_len, b + b_len);
+
+    if d == 0.0 {
+        if low < 0.0 && 0.0 < high { Some((NEG_INFINITY, INFINITY)) } else { None }
+    } else {
+        let (t1, t2) = (low / d, high / d);
+        if t1 < t2 { Some((t1, t2)) } else { Some((t2, t1)) }
+    }
 }
 
-pub fn test_rect_vert_line(a: &Rect<f32>, x: f32, width: f32) -> bool {
-    let (ax1, _, ax2, _) = a.ltrb();
+/// `sweep_rects` without wrapping, for a rect of size `a` at the origin
+fn sweep_from_origin(a: (f32, f32), delta: (f32, f32), b: (f32, f32, f32, f32)) -> Option<f32> {
+    use std::num::Float;
+
+    let (aw, ah) = a;
+    let (dx, dy) = delta;
+    let (bx, by, bw, bh) = b;
+
+    let (x_enter, x_exit) = match sweep_interval(aw, dx, bx, bw) { Some(t) => t, None => return None };
+    let (y_enter, y_exit) = match sweep_interval(ah, dy, by, bh) { Some(t) => t, None => return None };
+
+    let enter = Float::max(x_enter, y_enter);
+    let exit = Float::min(x_exit, y_exit);
+
+    if enter < exit && enter <= 1.0 && exit > 0.0 {
+        Some(Float::max(enter, 0.0))
+    } else {
+        None
+    }
+}
+
+/// How far `a` gets while moving by `delta` before it first touches `b`, as a fraction of `delta` from 0 to 1.
+/// Returns 0 if they overlap to begin with, and None if `a` never touches `b` along the way.
+pub fn sweep_rects(screen: &Screen, a: &Rect<f32>, delta: (f32, f32), b: &Rect<f32>) -> Option<f32> {
+    let (width, height) = screen.size();
+
+    // Where b is relative to a. The copies of b on the other sides of the seams are checked too,
+    // in case the path is long enough to reach them.
+    let (rx, ry) = screen.relative_wrap(a.left_top().xy(), b.left_top().xy());
+
+    let mut first: Option<f32> = None;
+    for &ox in [-width, 0.0, width].iter() {
+        for &oy in [-height, 0.0, height].iter() {
+            if let Some(t) = sweep_from_origin(a.size(), delta, (rx + ox, ry + oy, b.width(), b.height())) {
+                first = match first {
+                    Some(f) if f <= t => Some(f),
+                    _ => Some(t)
+                };
+            }
+        }
+    }
+
+    first
+}
+
+/// The smallest rect that covers `a` all the way along its move by `delta`
+pub fn sweep_bounds(screen: &Screen, a: &Rect<f32>, delta: (f32, f32)) -> Rect<f32> {
+    use std::num::Float;
+
+    let (dx, dy) = delta;
+    Rect::from_xywh(screen,
+        a.x() + Float::min(dx, 0.0),
+        a.y() + Float::min(dy, 0.0),
+        a.width() + Float::abs(dx),
+        a.height() + Float::abs(dy)
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::{test_rects, test_rect_point, test_rect_vert_line, sweep_rects, sweep_bounds};
+    use super::super::rect::Rect;
+    use super::super::wrapping::Screen;
+
+    fn screen() -> Screen {
+        Screen::new(100.0, 50.0, 10.0)
+    }
+
+    fn rect(x: f32, y: f32, w: f32, h: f32) -> Rect<f32> {
+        Rect::from_xywh(&screen(), x, y, w, h)
+    }
+
+    fn hit(a: &Rect<f32>, b: &Rect<f32>) -> bool {
+        let screen = screen();
+        let ab = test_rects(&screen, a, b);
+        assert!(ab == test_rects(&screen, b, a), "test_rects isn't symmetric");
+        ab
+    }
+
+    #[test]
+    fn overlapping() {
+        assert!(hit(&rect(10.0, 10.0, 10.0, 10.0), &rect(15.0, 15.0, 10.0, 10.0)));
+    }
+
+    #[test]
+    fn contained() {
+        assert!(hit(&rect(10.0, 10.0, 30.0, 30.0), &rect(15.0, 15.0, 5.0, 5.0)));
+    }
+
+    #[test]
+    fn cross_shaped() {
+        // Neither rect has a corner inside the other
+        assert!(hit(&rect(10.0, 20.0, 30.0, 5.0), &rect(20.0, 10.0, 5.0, 30.0)));
+    }
+
+    #[test]
+    fn apart() {
+        assert!(!hit(&rect(10.0, 10.0, 10.0, 10.0), &rect(30.0, 10.0, 10.0, 10.0)));
+        assert!(!hit(&rect(10.0, 10.0, 10.0, 10.0), &rect(10.0, 30.0, 10.0, 10.0)));
+    }
+
+    #[test]
+    fn touching_edges() {
+        assert!(!hit(&rect(10.0, 10.0, 10.0, 10.0), &rect(20.0, 10.0, 10.0, 10.0)));
+        assert!(!hit(&rect(10.0, 10.0, 10.0, 10.0), &rect(10.0, 20.0, 10.0, 10.0)));
+        assert!(!hit(&rect(10.0, 10.0, 10.0, 10.0), &rect(20.0, 20.0, 10.0, 10.0)));
+    }
+
+    #[test]
+    fn straddling_right_edge() {
+        assert!(hit(&rect(95.0, 10.0, 10.0, 10.0), &rect(0.0, 10.0, 10.0, 10.0)));
+        assert!(!hit(&rect(95.0, 10.0, 10.0, 10.0), &rect(5.0, 10.0, 10.0, 10.0)));
+    }
+
+    #[test]
+    fn straddling_bottom_edge() {
+        assert!(hit(&rect(10.0, 45.0, 10.0, 10.0), &rect(10.0, 0.0, 10.0, 10.0)));
+        assert!(!hit(&rect(10.0, 45.0, 10.0, 10.0), &rect(10.0, 5.0, 10.0, 10.0)));
+    }
+
+    #[test]
+    fn straddling_corner() {
+        let corner = rect(95.0, 45.0, 10.0, 10.0);
+        assert!(hit(&corner, &rect(0.0, 0.0, 3.0, 3.0)));
+        assert!(hit(&corner, &rect(90.0, 0.0, 8.0, 3.0)));
+        assert!(hit(&corner, &rect(0.0, 40.0, 3.0, 8.0)));
+        assert!(!hit(&corner, &rect(5.0, 5.0, 3.0, 3.0)));
+    }
+
+    #[test]
+    fn ending_on_edge() {
+        // Ends exactly at the right edge, so it doesn't wrap
+        assert!(!hit(&rect(90.0, 10.0, 10.0, 10.0), &rect(0.0, 10.0, 10.0, 10.0)));
+    }
+
+    #[test]
+    fn points() {
+        let screen = screen();
+        let a = rect(10.0, 10.0, 10.0, 10.0);
+
+        assert!(test_rect_point(&screen, &a, (10.0, 10.0)));
+        assert!(test_rect_point(&screen, &a, (19.0, 19.0)));
+        assert!(!test_rect_point(&screen, &a, (20.0, 15.0)));
+        assert!(!test_rect_point(&screen, &a, (15.0, 20.0)));
+        assert!(!test_rect_point(&screen, &a, (9.0, 15.0)));
+    }
+
+    #[test]
+    fn points_across_seams() {
+        let screen = screen();
+        let corner = rect(95.0, 45.0, 10.0, 10.0);
+
+        assert!(test_rect_point(&screen, &corner, (0.0, 0.0)));
+        assert!(test_rect_point(&screen, &corner, (97.0, 2.0)));
+        assert!(!test_rect_point(&screen, &corner, (5.0, 2.0)));
+
+        // Points past the edge are wrapped too
+        assert!(test_rect_point(&screen, &corner, (102.0, 52.0)));
+        assert!(test_rect_point(&screen, &rect(0.0, 0.0, 10.0, 10.0), (-95.0, 105.0)));
+    }
+
+    #[test]
+    fn vertical_lines() {
+        let screen = screen();
+
+        assert!(test_rect_vert_line(&screen, &rect(10.0, 10.0, 10.0, 10.0), 15.0));
+        assert!(!test_rect_vert_line(&screen, &rect(10.0, 10.0, 10.0, 10.0), 20.0));
+        assert!(test_rect_vert_line(&screen, &rect(95.0, 10.0, 10.0, 10.0), 2.0));
+        assert!(test_rect_vert_line(&screen, &rect(95.0, 10.0, 10.0, 10.0), 100.0));
+    }
+
+    #[test]
+    fn sweep_through_thin_rect() {
+        let screen = screen();
+        let bullet = rect(10.0, 10.0, 16.0, 1.0);
+        let wall = rect(40.0, 0.0, 2.0, 20.0);
+
+        // Jumps right over the wall if only the end position is tested
+        assert!(!hit(&bullet.offset(&screen, 40.0, 0.0), &wall));
+        assert!(sweep_rects(&screen, &bullet, (40.0, 0.0), &wall) == Some(0.35));
+    }
+
+    #[test]
+    fn sweep_misses() {
+        let screen = screen();
+        let bullet = rect(10.0, 10.0, 16.0, 1.0);
+
+        assert!(sweep_rects(&screen, &bullet, (8.0, 0.0), &rect(40.0, 0.0, 2.0, 20.0)) == None);
+        assert!(sweep_rects(&screen, &bullet, (-40.0, 0.0), &rect(40.0, 0.0, 2.0, 20.0)) == None);
+        assert!(sweep_rects(&screen, &bullet, (40.0, 0.0), &rect(40.0, 20.0, 2.0, 20.0)) == None);
+    }
+
+    #[test]
+    fn sweep_starting_inside() {
+        let screen = screen();
+        let bullet = rect(10.0, 10.0, 16.0, 1.0);
+
+        assert!(sweep_rects(&screen, &bullet, (8.0, 0.0), &rect(20.0, 0.0, 10.0, 20.0)) == Some(0.0));
+        assert!(sweep_rects(&screen, &bullet, (0.0, 0.0), &rect(20.0, 0.0, 10.0, 20.0)) == Some(0.0));
+    }
+
+    #[test]
+    fn sweep_across_seams() {
+        let screen = screen();
+
+        // Rightwards past the right edge
+        assert!(sweep_rects(&screen, &rect(90.0, 10.0, 4.0, 1.0), (16.0, 0.0), &rect(2.0, 0.0, 2.0, 20.0)) == Some(0.5));
+        // Leftwards past the left edge
+        assert!(sweep_rects(&screen, &rect(2.0, 10.0, 4.0, 1.0), (-16.0, 0.0), &rect(90.0, 0.0, 2.0, 20.0)) == Some(0.625));
+        // Downwards past the bottom edge
+        assert!(sweep_rects(&screen, &rect(10.0, 45.0, 1.0, 1.0), (0.0, 10.0), &rect(0.0, 2.0, 20.0, 2.0)) == Some(0.6));
+    }
+
+    #[test]
+    fn sweep_longer_than_half_the_screen() {
+        let screen = screen();
+        let bullet = rect(0.0, 10.0, 4.0, 1.0);
+
+        // The nearest copy of the wall is behind the bullet, but it gets to the one ahead
+        assert!(sweep_rects(&screen, &bullet, (80.0, 0.0), &rect(74.0, 0.0, 2.0, 20.0)) == Some(0.875));
+    }
+
+    #[test]
+    fn sweep_bounds_cover_the_path() {
+        let screen = screen();
+        let bounds = |a: &Rect<f32>, delta| {
+            let b = sweep_bounds(&screen, a, delta);
+            (b.left_top().xy(), b.size())
+        };
 
-    (x >= ax1 && x < ax2) || (x+width >= ax1 && x+width < ax2)
+        assert!(bounds(&rect(10.0, 10.0, 4.0, 1.0), (16.0, 0.0)) == ((10.0, 10.0), (20.0, 1.0)));
+        assert!(bounds(&rect(10.0, 10.0, 4.0, 1.0), (-16.0, 2.0)) == ((94.0, 10.0), (20.0, 3.0)));
+    }
 }
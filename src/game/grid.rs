@@ -0,0 +1,159 @@
+use super::rect::Rect;
+use super::wrapping::Screen;
+
+/// Finds the items near a rect without looking at every item.
+/// The screen is split into tile-sized cells, and each item is listed in every cell its rect touches.
+/// Rects that stick out past the edges of the screen wrap around, as everywhere else.
+#[derive(Clone)]
+pub struct Grid<T> {
+    columns: usize,
+    rows: usize,
+    cell_size: f32,
+    cells: Vec<Vec<T>>
+}
+
+impl<T: Copy + PartialEq> Grid<T> {
+    pub fn new(screen: &Screen) -> Grid<T> {
+        use std::num::Float;
+
+        let (width, height) = screen.size();
+        let cell_size = screen.tile_size;
+        let columns = Float::max(Float::ceil(width / cell_size), 1.0) as usize;
+        let rows = Float::max(Float::ceil(height / cell_size), 1.0) as usize;
+
+        Grid {
+            columns: columns,
+            rows: rows,
+            cell_size: cell_size,
+            cells: (0..columns * rows).map(|_| Vec::new()).collect()
+        }
+    }
+
+    pub fn insert(&mut self, rect: &Rect<f32>, item: T) {
+        for index in self.cells_in(rect) {
+            self.cells[index].push(item);
+        }
+    }
+
+    /// Takes out an item inserted with the same rect
+    pub fn remove(&mut self, rect: &Rect<f32>, item: T) {
+        for index in self.cells_in(rect) {
+            self.cells[index].retain(|&other| other != item);
+        }
+    }
+
+    /// Every item whose rect might touch `rect`, each listed once.
+    /// Items in the same cells that don't actually touch `rect` are included too.
+    pub fn query(&self, rect: &Rect<f32>) -> Vec<T> {
+        let mut found = Vec::new();
+
+        for index in self.cells_in(rect) {
+            for &item in self.cells[index].iter() {
+                if !found.contains(&item) {
+                    found.push(item);
+                }
+            }
+        }
+
+        found
+    }
+
+    /// The indices of the cells that `rect` touches
+    fn cells_in(&self, rect: &Rect<f32>) -> Vec<usize> {
+        let (x, y) = rect.left_top().xy();
+        let (w, h) = rect.size();
+
+        let columns = Grid::<T>::span(x, w, self.cell_size, self.columns);
+        let rows = Grid::<T>::span(y, h, self.cell_size, self.rows);
+
+        let mut cells = Vec::new();
+        for &row in rows.iter() {
+            for &column in columns.iter() {
+                cells.push(row * self.columns + column);
+            }
+        }
+        cells
+    }
+
+    /// The cells along one axis that [start, start + length) touches, wrapping around after `count`
+    fn span(start: f32, length: f32, cell_size: f32, count: usize) -> Vec<usize> {
+        use std::num::Float;
+
+        let first = Float::floor(start / cell_size) as i64;
+        // A rect that ends exactly on a cell boundary doesn't touch the next cell
+        let last = if length > 0.0 { Float::ceil((start + length) / cell_size) as i64 - 1 } else { first };
+        let spanned = (last - first + 1) as usize;
+        // Anything longer than the screen touches every cell
+        let spanned = if spanned < count { spanned } else { count };
+
+        let count_i = count as i64;
+        (0..spanned).map(|i| {
+            (((first + i as i64) % count_i + count_i) % count_i) as usize
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Grid;
+    use super::super::rect::Rect;
+    use super::super::wrapping::Screen;
+
+    fn screen() -> Screen {
+        Screen::new(160.0, 80.0, 16.0)
+    }
+
+    fn rect(x: f32, y: f32, w: f32, h: f32) -> Rect<f32> {
+        Rect::from_xywh(&screen(), x, y, w, h)
+    }
+
+    #[test]
+    fn finds_nearby_items() {
+        let mut grid = Grid::new(&screen());
+        grid.insert(&rect(16.0, 16.0, 16.0, 16.0), 1);
+        grid.insert(&rect(100.0, 50.0, 16.0, 16.0), 2);
+
+        assert!(grid.query(&rect(20.0, 20.0, 4.0, 4.0)) == vec![1]);
+        assert!(grid.query(&rect(108.0, 60.0, 4.0, 4.0)) == vec![2]);
+        assert!(grid.query(&rect(60.0, 20.0, 4.0, 4.0)).is_empty());
+    }
+
+    #[test]
+    fn lists_items_once() {
+        let mut grid = Grid::new(&screen());
+        grid.insert(&rect(8.0, 8.0, 40.0, 40.0), 1);
+
+        assert!(grid.query(&rect(0.0, 0.0, 64.0, 64.0)) == vec![1]);
+    }
+
+    #[test]
+    fn cell_boundaries() {
+        let mut grid = Grid::new(&screen());
+        grid.insert(&rect(16.0, 16.0, 16.0, 16.0), 1);
+
+        // Ends right where the item's cell starts
+        assert!(grid.query(&rect(0.0, 16.0, 16.0, 16.0)).is_empty());
+        assert!(grid.query(&rect(0.0, 16.0, 17.0, 16.0)) == vec![1]);
+    }
+
+    #[test]
+    fn wraps_around() {
+        let mut grid = Grid::new(&screen());
+        grid.insert(&rect(150.0, 75.0, 16.0, 16.0), 1);
+
+        assert!(grid.query(&rect(0.0, 0.0, 4.0, 4.0)) == vec![1]);
+        assert!(grid.query(&rect(152.0, 0.0, 4.0, 4.0)) == vec![1]);
+        assert!(grid.query(&rect(0.0, 70.0, 4.0, 4.0)) == vec![1]);
+        assert!(grid.query(&rect(32.0, 32.0, 4.0, 4.0)).is_empty());
+    }
+
+    #[test]
+    fn remove() {
+        let mut grid = Grid::new(&screen());
+        grid.insert(&rect(16.0, 16.0, 16.0, 16.0), 1);
+        grid.insert(&rect(24.0, 16.0, 16.0, 16.0), 2);
+        grid.remove(&rect(16.0, 16.0, 16.0, 16.0), 1);
+
+        assert!(grid.query(&rect(16.0, 16.0, 32.0, 16.0)) == vec![2]);
+    }
+}
@@ -0,0 +1,342 @@
+use super::audio::Audio;
+use super::items::DynamicItems;
+use super::player::Player;
+use super::wrapping::Screen;
+
+/// The item sticky keys turn into once they're taken
+pub static KEY_ITEM: &'static str = "key";
+
+/// An item type, by its index in `ItemTypes`
+pub type ItemId = usize;
+
+/// What holding a passive item lets the player do
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Ability {
+    /// Dig through dirt entrances
+    Dig,
+    /// Open a door. Uses up the item.
+    Unlock
+}
+
+/// Everything an active item can affect when it's used
+pub struct UseContext<'a, 'sdl: 'a> {
+    pub screen: &'a Screen,
+    pub player: &'a Player,
+    pub items: &'a mut DynamicItems,
+    pub audio: Option<&'a mut Audio<'sdl>>
+}
+
+/// Uses an active item. Does nothing if it can't be used just then.
+pub type UseAction = fn(&mut UseContext);
+
+pub enum ItemUse {
+    /// Works just by being carried
+    Passive(Ability),
+    /// Works when it's the selected item and the player presses Fire
+    Active(UseAction)
+}
+
+pub struct ItemType {
+    /// Used by the `contains` property of chests
+    pub name: String,
+    /// How many of the item the player can carry
+    pub stack_size: u32,
+    pub usage: ItemUse,
+    /// Drawn in the player's hand while it's the selected item
+    pub held_tile: Option<u16>
+}
+
+impl ItemType {
+    pub fn is_active(&self) -> bool {
+        match self.usage {
+            ItemUse::Active(_) => true,
+            ItemUse::Passive(_) => false
+        }
+    }
+
+    pub fn ability(&self) -> Option<Ability> {
+        match self.usage {
+            ItemUse::Passive(ability) => Some(ability),
+            ItemUse::Active(_) => None
+        }
+    }
+}
+
+/// Every item the player can carry
+pub struct ItemTypes {
+    types: Vec<ItemType>
+}
+
+impl ItemTypes {
+    /// No items at all
+    pub fn new() -> ItemTypes {
+        ItemTypes {
+            types: Vec::new()
+        }
+    }
+
+    /// Every item the game knows about
+    pub fn builtin() -> ItemTypes {
+        let mut types = ItemTypes::new();
+        types.register(ItemType {
+            name: "drill".to_string(),
+            stack_size: 1,
+            usage: ItemUse::Passive(Ability::Dig),
+            held_tile: None
+        });
+        types.register(ItemType {
+            name: "gun".to_string(),
+            stack_size: 1,
+            usage: ItemUse::Active(fire_gun),
+            held_tile: Some(0x3B)
+        });
+        types.register(ItemType {
+            name: KEY_ITEM.to_string(),
+            stack_size: 99,
+            usage: ItemUse::Passive(Ability::Unlock),
+            held_tile: None
+        });
+        types
+    }
+
+    /// Replaces any item already registered with the same name. Returns the item's id.
+    pub fn register(&mut self, item: ItemType) -> ItemId {
+        match self.find(&item.name) {
+            Some(id) => {
+                self.types[id] = item;
+                id
+            },
+            None => {
+                self.types.push(item);
+                self.types.len() - 1
+            }
+        }
+    }
+
+    pub fn find(&self, name: &str) -> Option<ItemId> {
+        self.types.iter().position(|t| t.name == name)
+    }
+
+    pub fn get(&self, id: ItemId) -> &ItemType {
+        &self.types[id]
+    }
+}
+
+#[derive(Clone, PartialEq)]
+struct Slot {
+    item: ItemId,
+    count: u32
+}
+
+/// What the player is carrying
+#[derive(Clone, PartialEq)]
+pub struct Inventory {
+    /// In the order the items were picked up. Never holds a count of 0.
+    slots: Vec<Slot>,
+    /// The slot of the active item that Fire uses
+    selected: Option<usize>
+}
+
+impl Inventory {
+    pub fn new() -> Inventory {
+        Inventory {
+            slots: Vec::new(),
+            selected: None
+        }
+    }
+
+    pub fn count(&self, item: ItemId) -> u32 {
+        match self.slots.iter().find(|s| s.item == item) {
+            Some(slot) => slot.count,
+            None => 0
+        }
+    }
+
+    /// Adds as many as `count` of an item, or as many as its stack has room for.
+    /// Returns how many were added.
+    pub fn add(&mut self, types: &ItemTypes, item: ItemId, count: u32) -> u32 {
+        use std::cmp::min;
+
+        let item_type = types.get(item);
+        let added = min(count, item_type.stack_size.saturating_sub(self.count(item)));
+        if added == 0 {
+            return 0;
+        }
+
+        let index = match self.slots.iter().position(|s| s.item == item) {
+            Some(index) => index,
+            None => {
+                self.slots.push(Slot { item: item, count: 0 });
+                self.slots.len() - 1
+            }
+        };
+        self.slots[index].count += added;
+
+        // The first active item is ready to use straight away
+        if self.selected.is_none() && item_type.is_active() {
+            self.selected = Some(index);
+        }
+
+        added
+    }
+
+    /// Takes away `count` of an item. Returns false, and takes nothing, if there aren't that many.
+    pub fn remove(&mut self, types: &ItemTypes, item: ItemId, count: u32) -> bool {
+        let index = match self.slots.iter().position(|s| s.item == item) {
+            Some(index) => index,
+            None => return count == 0
+        };
+        if self.slots[index].count < count {
+            return false;
+        }
+
+        self.slots[index].count -= count;
+
+        if self.slots[index].count == 0 {
+            self.slots.remove(index);
+            self.selected = match self.selected {
+                Some(selected) if selected == index => None,
+                Some(selected) if selected > index => Some(selected - 1),
+                selected => selected
+            };
+            if self.selected.is_none() {
+                self.select_next(types);
+            }
+        }
+
+        true
+    }
+
+    /// Adds or removes an item until there are `count` of it
+    pub fn set_count(&mut self, types: &ItemTypes, item: ItemId, count: u32) {
+        let current = self.count(item);
+        if current > count {
+            self.remove(types, item, current - count);
+        } else {
+            self.add(types, item, count - current);
+        }
+    }
+
+    pub fn has_ability(&self, types: &ItemTypes, ability: Ability) -> bool {
+        self.slots.iter().any(|s| types.get(s.item).ability() == Some(ability))
+    }
+
+    /// Uses up one of the items that give `ability`. Returns false if there aren't any.
+    pub fn use_up(&mut self, types: &ItemTypes, ability: Ability) -> bool {
+        match self.slots.iter().find(|s| types.get(s.item).ability() == Some(ability)).map(|s| s.item) {
+            Some(item) => self.remove(types, item, 1),
+            None => false
+        }
+    }
+
+    /// The active item that Fire uses
+    pub fn selected(&self) -> Option<ItemId> {
+        self.selected.map(|index| self.slots[index].item)
+    }
+
+    /// Selects the next active item, going back to the first one after the last
+    pub fn select_next(&mut self, types: &ItemTypes) {
+        let count = self.slots.len();
+        let start = match self.selected {
+            Some(index) => index + 1,
+            None => 0
+        };
+
+        let next = (0..count).map(|i| (start + i) % count).find(|&index| types.get(self.slots[index].item).is_active());
+        if next.is_some() {
+            self.selected = next;
+        }
+    }
+}
+
+fn fire_gun(c: &mut UseContext) {
+    use super::player::PlayerState;
+    use super::player::PlayerStandDirection::{Left, Right};
+
+    if let PlayerState::Stand(ref s) = c.player.state {
+        let (bullet_coord, vel_x) = match s.direction {
+            Left => (s.xy.offset(c.screen, -4.0, 12.0), -8.0),
+            Right => (s.xy.offset(c.screen, 20.0, 12.0), 8.0)
+        };
+        c.items.add_bullet(bullet_coord, vel_x);
+
+        if let Some(ref mut audio) = c.audio {
+            audio.fire();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Ability, Inventory, ItemType, ItemTypes, ItemUse, UseContext, KEY_ITEM};
+
+    fn nothing(_: &mut UseContext) {}
+
+    fn types() -> ItemTypes {
+        let mut types = ItemTypes::builtin();
+        types.register(ItemType {
+            name: "rope".to_string(),
+            stack_size: 1,
+            usage: ItemUse::Active(nothing),
+            held_tile: None
+        });
+        types
+    }
+
+    #[test]
+    fn stacks_fill_up() {
+        let types = types();
+        let key = types.find(KEY_ITEM).unwrap();
+        let mut inventory = Inventory::new();
+
+        assert!(inventory.add(&types, key, 98) == 98);
+        assert!(inventory.add(&types, key, 3) == 1);
+        assert!(inventory.count(key) == 99);
+
+        assert!(!inventory.remove(&types, key, 100));
+        assert!(inventory.remove(&types, key, 99));
+        assert!(inventory.count(key) == 0);
+    }
+
+    #[test]
+    fn abilities() {
+        let types = types();
+        let mut inventory = Inventory::new();
+
+        assert!(!inventory.has_ability(&types, Ability::Dig));
+        inventory.add(&types, types.find("drill").unwrap(), 1);
+        assert!(inventory.has_ability(&types, Ability::Dig));
+
+        inventory.add(&types, types.find(KEY_ITEM).unwrap(), 1);
+        assert!(inventory.use_up(&types, Ability::Unlock));
+        assert!(!inventory.use_up(&types, Ability::Unlock));
+        // The drill isn't used up by digging
+        assert!(inventory.has_ability(&types, Ability::Dig));
+    }
+
+    #[test]
+    fn switching_tools() {
+        let types = types();
+        let gun = types.find("gun").unwrap();
+        let rope = types.find("rope").unwrap();
+        let mut inventory = Inventory::new();
+
+        inventory.add(&types, types.find("drill").unwrap(), 1);
+        assert!(inventory.selected() == None);
+
+        inventory.add(&types, gun, 1);
+        inventory.add(&types, rope, 1);
+        assert!(inventory.selected() == Some(gun));
+
+        inventory.select_next(&types);
+        assert!(inventory.selected() == Some(rope));
+        inventory.select_next(&types);
+        assert!(inventory.selected() == Some(gun));
+
+        // Losing the selected tool selects another
+        inventory.remove(&types, gun, 1);
+        assert!(inventory.selected() == Some(rope));
+        inventory.remove(&types, rope, 1);
+        assert!(inventory.selected() == None);
+    }
+}
@@ -0,0 +1,98 @@
+use std::any::Any;
+use rustc_serialize::json::{Json, Object};
+use super::{spawn_signal, same_placed_item, DrawContext, Item, ItemContext, PlacedItem};
+use super::super::level::{LevelError, LevelObjects, ObjectContext, ObjectWriter, Signal, TriggerId};
+use super::super::rect::{Point, Rect};
+use super::super::wrapping::Screen;
+
+#[derive(PartialEq)]
+pub struct PlacedBeanstalk {
+    pub x: f32,
+    pub y: f32,
+    pub height: u32,
+    pub triggered_by: Option<TriggerId>,
+    pub poof: bool,
+}
+
+pub fn parse(o: &ObjectContext, objects: &mut LevelObjects) -> Result<(), LevelError> {
+    let triggered_by = try!(o.trigger("triggered_by"));
+    let poof = try!(o.boolean("poof")).unwrap_or(false);
+
+    objects.items.push(Box::new(PlacedBeanstalk {
+        x: o.x,
+        y: o.y,
+        height: (o.height / o.tile_size) as u32,
+        triggered_by: triggered_by,
+        poof: poof,
+    }));
+
+    Ok(())
+}
+
+impl PlacedItem for PlacedBeanstalk {
+    fn spawn(&self, screen: &Screen, _steps_per_second: u32) -> Box<Item> {
+        Box::new(Beanstalk {
+            xy: Point::new(screen, (self.x, self.y)),
+            height: self.height,
+            visible: false,
+            triggered_by: self.triggered_by,
+            tile_size: screen.tile_size
+        })
+    }
+
+    fn export(&self, w: &ObjectWriter) -> Json {
+        let mut p = Object::new();
+        w.set_optional_trigger(&mut p, "triggered_by", self.triggered_by);
+        w.set(&mut p, "poof", self.poof.to_string());
+        w.object("beanstalk", self.x, self.y, w.tile_size, self.height as f32 * w.tile_size, p)
+    }
+
+    fn as_any(&self) -> &Any { self }
+
+    fn equals(&self, other: &PlacedItem) -> bool { same_placed_item(self, other) }
+}
+
+#[derive(Clone)]
+pub struct Beanstalk {
+    pub xy: Point<f32>,
+    pub height: u32,
+    pub visible: bool,
+    triggered_by: Option<TriggerId>,
+    tile_size: f32
+}
+
+impl Item for Beanstalk {
+    fn rect(&self) -> Option<Rect<f32>> {
+        if self.visible { Some(Rect::new(self.xy, (self.tile_size, self.height as f32 * self.tile_size))) } else { None }
+    }
+
+    fn clone_item(&self) -> Box<Item> { Box::new(self.clone()) }
+
+    fn signal(&mut self, signal: Signal, c: &mut ItemContext) -> bool {
+        match spawn_signal(self.triggered_by, signal) {
+            Some(visible) if visible != self.visible => {
+                self.visible = visible;
+                let screen = c.screen;
+                for y in (0..self.height) {
+                    c.poof_around(self.xy.offset(screen, 0.0, y as f32 * self.tile_size));
+                }
+                true
+            },
+            _ => false
+        }
+    }
+
+    fn is_climbable(&self) -> bool { true }
+
+    fn draw(&self, d: &DrawContext) {
+        if self.visible {
+            for y in (0..self.height) {
+                let tile = match y % 2 {
+                    0 => 0x0E,
+                    _ => 0x0F
+                };
+                d.tile(self.xy.offset(d.screen, 0.0, y as f32 * d.tile_size), tile);
+            }
+        }
+    }
+}
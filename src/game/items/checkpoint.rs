@@ -0,0 +1,94 @@
+use std::any::Any;
+use rustc_serialize::json::{Json, Object};
+use super::{same_placed_item, DrawContext, Item, ItemContext, ItemEvent, PlacedItem, Touch};
+use super::super::audio::Sound;
+use super::super::level::{LevelError, LevelObjects, ObjectContext, ObjectWriter, Signal, TriggerId};
+use super::super::rect::{Point, Rect};
+use super::super::wrapping::Screen;
+
+/// Where the player comes back after dying, once they've reached it
+#[derive(PartialEq)]
+pub struct PlacedCheckpoint {
+    pub x: f32,
+    pub y: f32,
+    /// Fired whenever the player reaches the checkpoint
+    pub trigger: Option<TriggerId>
+}
+
+pub fn parse(o: &ObjectContext, objects: &mut LevelObjects) -> Result<(), LevelError> {
+    let trigger = try!(o.trigger("trigger"));
+
+    objects.items.push(Box::new(PlacedCheckpoint {
+        x: o.x,
+        y: o.y,
+        trigger: trigger
+    }));
+
+    Ok(())
+}
+
+impl PlacedItem for PlacedCheckpoint {
+    fn spawn(&self, screen: &Screen, _steps_per_second: u32) -> Box<Item> {
+        Box::new(Checkpoint {
+            xy: Point::new(screen, (self.x, self.y)),
+            active: false,
+            trigger: self.trigger,
+            tile_size: screen.tile_size
+        })
+    }
+
+    fn export(&self, w: &ObjectWriter) -> Json {
+        let mut p = Object::new();
+        w.set_optional_trigger(&mut p, "trigger", self.trigger);
+        w.object("checkpoint", self.x, self.y, w.tile_size, w.tile_size, p)
+    }
+
+    fn as_any(&self) -> &Any { self }
+
+    fn equals(&self, other: &PlacedItem) -> bool { same_placed_item(self, other) }
+}
+
+#[derive(Clone)]
+pub struct Checkpoint {
+    pub xy: Point<f32>,
+    /// Whether this is where the player respawns
+    pub active: bool,
+    pub trigger: Option<TriggerId>,
+    tile_size: f32
+}
+
+impl Item for Checkpoint {
+    fn rect(&self) -> Option<Rect<f32>> {
+        Some(Rect::new(self.xy, (self.tile_size, self.tile_size)))
+    }
+
+    fn clone_item(&self) -> Box<Item> { Box::new(self.clone()) }
+
+    /// Becomes the active checkpoint when the player reaches it alive
+    fn touch(&mut self, touch: &Touch, c: &mut ItemContext) {
+        if !(touch.touching && touch.alive && !self.active) {
+            return;
+        }
+
+        self.active = true;
+
+        let screen = c.screen;
+        c.effects.add_poof(self.xy.offset(screen, -5.0, -5.0));
+        c.effects.add_poof(self.xy.offset(screen, 5.0, -8.0));
+
+        c.send(ItemEvent::Checkpoint(self.xy));
+        c.send(ItemEvent::Sound(Sound::Checkpoint));
+        if let Some(trigger) = self.trigger {
+            c.send(ItemEvent::Signal(Signal::On(trigger)));
+        }
+    }
+
+    fn leave_checkpoint(&mut self) {
+        self.active = false;
+    }
+
+    /// A running man sign, lying upside down until the player reaches it
+    fn draw(&self, d: &DrawContext) {
+        d.flipped_tile(self.xy, 0x2C, (false, !self.active));
+    }
+}
@@ -0,0 +1,246 @@
+use std::any::Any;
+use rustc_serialize::json::{Json, Object};
+use super::{spawn_signal, same_placed_item, tile_from_phase, DrawContext, Item, ItemContext, ItemEvent, PlacedItem, Touch};
+use super::super::audio::Sound;
+use super::super::inventory::ItemTypes;
+use super::super::level::{LevelError, LevelObjects, ObjectContext, ObjectWriter, Signal, TriggerId};
+use super::super::rect::{Point, Rect};
+use super::super::wrapping::Screen;
+
+#[derive(Clone, PartialEq)]
+pub enum ChestItem {
+    UselessPoints,
+    /// One of `ItemTypes`, by name
+    Item(String),
+    None
+}
+
+impl ChestItem {
+    /// The name used by the `contains` property in level files.
+    /// Besides `useless` and `none`, it's the name of an item in `items`.
+    pub fn from_name(name: &str, items: &ItemTypes) -> Option<ChestItem> {
+        match name {
+            "useless" => Some(ChestItem::UselessPoints),
+            "none" => Some(ChestItem::None),
+            _ => items.find(name).map(|_| ChestItem::Item(name.to_string()))
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match *self {
+            ChestItem::UselessPoints => "useless",
+            ChestItem::Item(ref name) => name,
+            ChestItem::None => "none"
+        }
+    }
+}
+
+#[derive(PartialEq)]
+pub struct PlacedChest {
+    pub x: f32,
+    pub y: f32,
+    pub trigger: Option<TriggerId>,
+    pub explode_trigger: Option<TriggerId>,
+    pub triggered_by: Option<TriggerId>,
+    pub poof: bool,
+    pub is_static: bool,
+    pub fall_distance: f32,
+    pub contains: ChestItem
+}
+
+pub fn parse(o: &ObjectContext, objects: &mut LevelObjects) -> Result<(), LevelError> {
+    let trigger = try!(o.trigger("trigger"));
+    let explode_trigger = try!(o.trigger("explode_trigger"));
+    let triggered_by = try!(o.trigger("triggered_by"));
+    let poof = try!(o.boolean("poof")).unwrap_or(false);
+    let is_static = try!(o.boolean("static")).unwrap_or(false);
+    let contains = {
+        let name = try!(o.require("contains", o.string("contains")));
+        match ChestItem::from_name(&name, o.items) {
+            Some(item) => item,
+            None => return Err(LevelError::InvalidProperty(o.location.clone(), "contains".to_string(), "useless, none or a known item"))
+        }
+    };
+
+    objects.items.push(Box::new(PlacedChest {
+        x: o.x,
+        y: o.y,
+        trigger: trigger,
+        explode_trigger: explode_trigger,
+        triggered_by: triggered_by,
+        poof: poof,
+        is_static: is_static,
+        fall_distance: o.height - o.tile_size,
+        contains: contains
+    }));
+
+    Ok(())
+}
+
+impl PlacedItem for PlacedChest {
+    fn spawn(&self, screen: &Screen, _steps_per_second: u32) -> Box<Item> {
+        Box::new(Chest {
+            triggered_by: self.triggered_by,
+            trigger: self.trigger,
+            explode_trigger: self.explode_trigger,
+            xy: Point::new(screen, (self.x, self.y)),
+            visible: self.triggered_by.is_none(),
+            phase: 0.0,
+            is_static: self.is_static,
+            original_xy: Point::new(screen, (self.x, self.y)),
+            fall_distance: self.fall_distance,
+            fall_phase: 0.0,
+            opened: false,
+            contains: self.contains.clone(),
+            tile_size: screen.tile_size
+        })
+    }
+
+    fn export(&self, w: &ObjectWriter) -> Json {
+        let mut p = Object::new();
+        w.set_optional_trigger(&mut p, "trigger", self.trigger);
+        w.set_optional_trigger(&mut p, "explode_trigger", self.explode_trigger);
+        w.set_optional_trigger(&mut p, "triggered_by", self.triggered_by);
+        w.set(&mut p, "poof", self.poof.to_string());
+        w.set(&mut p, "static", self.is_static.to_string());
+        w.set(&mut p, "contains", self.contains.name().to_string());
+        w.object("chest", self.x, self.y, w.tile_size, self.fall_distance + w.tile_size, p)
+    }
+
+    fn as_any(&self) -> &Any { self }
+
+    fn equals(&self, other: &PlacedItem) -> bool { same_placed_item(self, other) }
+}
+
+#[derive(Clone)]
+pub struct Chest {
+    pub triggered_by: Option<TriggerId>,
+    pub trigger: Option<TriggerId>,
+    pub explode_trigger: Option<TriggerId>,
+    pub xy: Point<f32>,
+    pub visible: bool,
+    pub phase: f32,
+    pub is_static: bool,
+    opened: bool,
+    original_xy: Point<f32>,
+    fall_distance: f32,
+    fall_phase: f32,
+    contains: ChestItem,
+    tile_size: f32
+}
+
+impl Chest {
+    pub fn spawn(&mut self) {
+        self.xy = self.original_xy;
+        self.visible = true;
+        self.fall_phase = 0.0;
+        // A chest that comes back is full again
+        self.opened = false;
+        self.phase = 0.0;
+    }
+}
+
+impl Item for Chest {
+    fn rect(&self) -> Option<Rect<f32>> {
+        if self.visible { Some(Rect::new(self.xy, (self.tile_size, self.tile_size))) } else { None }
+    }
+
+    fn clone_item(&self) -> Box<Item> { Box::new(self.clone()) }
+
+    fn signal(&mut self, signal: Signal, c: &mut ItemContext) -> bool {
+        match spawn_signal(self.triggered_by, signal) {
+            Some(true) if !self.visible => self.spawn(),
+            Some(false) if self.visible => self.visible = false,
+            _ => return false
+        }
+        c.poof_around(self.xy);
+        true
+    }
+
+    fn step(&mut self, c: &mut ItemContext) {
+        fn lerp(a: f32, b: f32, p: f32) -> f32 { (b-a)*p + a }
+        fn curve(x: f32) -> f32 {
+            use std::num::Float;
+            use std::f32::consts::FRAC_PI_2;
+
+            let coeff = 1.4;
+
+            1.0 - Float::sin(((x*coeff)-coeff)*FRAC_PI_2) / Float::sin((-coeff)*FRAC_PI_2)
+        }
+
+        if !self.visible {
+            return;
+        }
+
+        if self.fall_phase < 1.0 {
+            let fall_rate = if self.fall_distance == 0.0 { 1.0 } else { self.tile_size / self.fall_distance };
+
+            self.fall_phase += 0.06 * fall_rate;
+            if self.fall_phase > 1.0 { self.fall_phase = 1.0 }
+            let y = lerp(self.original_xy.y(), self.original_xy.y() + self.fall_distance, curve(self.fall_phase));
+            self.xy = self.xy.set_y(c.screen, y);
+        }
+
+        if self.opened && self.phase < 1.0 {
+            self.phase += 0.03;
+            if self.phase > 1.0 { self.phase = 1.0 }
+        }
+    }
+
+    /// Opens the chest when the player presses Down on it
+    fn touch(&mut self, touch: &Touch, c: &mut ItemContext) {
+        if !(touch.touching && touch.opening && !self.opened) {
+            return;
+        }
+
+        self.opened = true;
+        if let Some(trigger) = self.trigger {
+            c.send(ItemEvent::Signal(Signal::On(trigger)));
+        }
+
+        match self.contains {
+            ChestItem::Item(ref name) => c.send(ItemEvent::Give(name.clone(), 1)),
+            ChestItem::UselessPoints => {
+                c.effects.add_useless_points(self.xy);
+                c.send(ItemEvent::Sound(Sound::Nothing));
+            },
+            ChestItem::None => ()
+        }
+
+        let screen = c.screen;
+        c.effects.add_poof(touch.player.left_top().offset(screen, 5.0, 5.0));
+        c.send(ItemEvent::Sound(Sound::ItemGet));
+    }
+
+    fn is_shootable(&self) -> bool { true }
+
+    fn shot(&mut self, c: &mut ItemContext) {
+        c.effects.add_poof(self.xy);
+        self.visible = false;
+    }
+
+    fn is_pushable(&self) -> bool { !self.is_static }
+
+    fn move_to(&mut self, xy: Point<f32>) {
+        self.xy = xy;
+    }
+
+    fn crush(&mut self, c: &mut ItemContext) {
+        self.visible = false;
+        if let Some(trigger) = self.explode_trigger {
+            c.send(ItemEvent::Signal(Signal::On(trigger)));
+        }
+        c.effects.add_poof(self.xy);
+    }
+
+    fn draw(&self, d: &DrawContext) {
+        if self.visible {
+            let tile_offset = match self.is_static {
+                true => 0,
+                false => 5
+            };
+            let tile = tile_from_phase(&[0x04, 0x05, 0x06, 0x07, 0x08], self.phase) + tile_offset;
+            d.tile(self.xy.offset(d.screen, 0.0, d.tile_size * 3.0/16.0), tile);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use std::any::Any;
+use rustc_serialize::json::{Json, Object};
+use super::{same_placed_item, DrawContext, Item, ItemContext, ItemEvent, PlacedItem, Touch};
+use super::super::audio::Sound;
+use super::super::inventory::KEY_ITEM;
+use super::super::level::{LevelError, LevelObjects, ObjectContext, ObjectWriter};
+use super::super::rect::{Point, Rect};
+use super::super::wrapping::Screen;
+
+/// A key stuck in the wall until it's shot loose
+#[derive(PartialEq)]
+pub struct PlacedStickyKey {
+    pub x: f32,
+    pub y: f32,
+    pub fall_distance: f32
+}
+
+pub fn parse(o: &ObjectContext, objects: &mut LevelObjects) -> Result<(), LevelError> {
+    objects.items.push(Box::new(PlacedStickyKey {
+        x: o.x,
+        y: o.y,
+        fall_distance: o.height - o.tile_size / 2.0
+    }));
+
+    Ok(())
+}
+
+impl PlacedItem for PlacedStickyKey {
+    fn spawn(&self, screen: &Screen, _steps_per_second: u32) -> Box<Item> {
+        Box::new(Key {
+            xy: Point::new(screen, (self.x, self.y)),
+            is_sticky: true,
+            visible: true,
+            vel_y: 0.0,
+            to_y: self.y + self.fall_distance,
+            tile_size: screen.tile_size
+        })
+    }
+
+    fn export(&self, w: &ObjectWriter) -> Json {
+        w.object("stickykey", self.x, self.y, w.tile_size, self.fall_distance + w.tile_size / 2.0, Object::new())
+    }
+
+    fn as_any(&self) -> &Any { self }
+
+    fn equals(&self, other: &PlacedItem) -> bool { same_placed_item(self, other) }
+}
+
+#[derive(Clone)]
+pub struct Key {
+    pub xy: Point<f32>,
+    pub is_sticky: bool,
+    pub visible: bool,
+    vel_y: f32,
+    to_y: f32,
+    tile_size: f32
+}
+
+impl Key {
+    pub fn is_free(&self) -> bool {
+        !self.is_sticky && self.visible
+    }
+
+    pub fn unstick(&mut self) {
+        self.is_sticky = false;
+    }
+}
+
+impl Item for Key {
+    fn rect(&self) -> Option<Rect<f32>> {
+        if self.visible { Some(Rect::new(self.xy, (self.tile_size, self.tile_size))) } else { None }
+    }
+
+    fn clone_item(&self) -> Box<Item> { Box::new(self.clone()) }
+
+    fn step(&mut self, c: &mut ItemContext) {
+        if !self.is_free() {
+            return;
+        }
+
+        let p = self.xy.offset(c.screen, 0.0, self.vel_y);
+        self.xy = if p.xy().1 > self.to_y { p.set_y(c.screen, self.to_y) } else { p };
+
+        self.vel_y += 0.1;
+    }
+
+    /// Free keys are picked up by touching them
+    fn touch(&mut self, touch: &Touch, c: &mut ItemContext) {
+        if touch.touching && self.is_free() {
+            self.visible = false;
+            c.send(ItemEvent::Give(KEY_ITEM.to_string(), 1));
+            c.send(ItemEvent::Sound(Sound::KeyGet));
+        }
+    }
+
+    fn is_shootable(&self) -> bool { self.is_sticky }
+
+    fn shot(&mut self, _c: &mut ItemContext) {
+        self.unstick();
+    }
+
+    fn is_restored_with_doors(&self) -> bool { true }
+
+    fn draw(&self, d: &DrawContext) {
+        if self.visible {
+            let tile = match self.is_sticky {
+                true => 0x2E,
+                false => 0x2F
+            };
+            d.tile(self.xy, tile);
+        }
+    }
+
+    fn is_in_front(&self) -> bool { true }
+}
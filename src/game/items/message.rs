@@ -0,0 +1,102 @@
+use std::any::Any;
+use rustc_serialize::json::{Json, Object};
+use super::{spawn_signal, same_placed_item, DrawContext, Item, ItemContext, PlacedItem};
+use super::super::level::{LevelError, LevelObjects, ObjectContext, ObjectWriter, Signal, TriggerId};
+use super::super::rect::{Point, Rect};
+use super::super::wrapping::Screen;
+
+#[derive(PartialEq)]
+pub struct PlacedMessage {
+    pub x: f32,
+    pub y: f32,
+    pub width: u32,
+    pub height: u32,
+    pub tiles: Vec<u16>,
+    pub triggered_by: Option<TriggerId>
+}
+
+pub fn parse(o: &ObjectContext, objects: &mut LevelObjects) -> Result<(), LevelError> {
+    let triggered_by = try!(o.trigger("triggered_by"));
+
+    let message_tiles = try!(o.tiles("tiles"));
+    let w = (o.width / o.tile_size) as u32;
+    let h = (o.height / o.tile_size) as u32;
+
+    if message_tiles.len() != (w*h) as usize {
+        return Err(LevelError::WrongTileCount(o.location.clone(), (w*h) as usize, message_tiles.len()));
+    }
+
+    objects.items.push(Box::new(PlacedMessage {
+        x: o.x,
+        y: o.y,
+        width: w,
+        height: h,
+        tiles: message_tiles,
+        triggered_by: triggered_by
+    }));
+
+    Ok(())
+}
+
+impl PlacedItem for PlacedMessage {
+    fn spawn(&self, screen: &Screen, _steps_per_second: u32) -> Box<Item> {
+        Box::new(Message {
+            xy: Point::new(screen, (self.x, self.y)),
+            width: self.width,
+            height: self.height,
+            tiles: self.tiles.clone(),
+            triggered_by: self.triggered_by,
+            visible: false,
+        })
+    }
+
+    fn export(&self, w: &ObjectWriter) -> Json {
+        let mut p = Object::new();
+        w.set_optional_trigger(&mut p, "triggered_by", self.triggered_by);
+        let tiles: Vec<String> = self.tiles.iter().map(|t| format!("{:x}", t)).collect();
+        w.set(&mut p, "tiles", tiles.connect(" "));
+        w.object("message", self.x, self.y, self.width as f32 * w.tile_size, self.height as f32 * w.tile_size, p)
+    }
+
+    fn as_any(&self) -> &Any { self }
+
+    fn equals(&self, other: &PlacedItem) -> bool { same_placed_item(self, other) }
+}
+
+#[derive(Clone)]
+pub struct Message {
+    pub xy: Point<f32>,
+    pub width: u32,
+    pub height: u32,
+    pub tiles: Vec<u16>,
+    pub triggered_by: Option<TriggerId>,
+    pub visible: bool,
+}
+
+impl Item for Message {
+    /// Messages are only ever looked at
+    fn rect(&self) -> Option<Rect<f32>> { None }
+
+    fn signal(&mut self, signal: Signal, _c: &mut ItemContext) -> bool {
+        match spawn_signal(self.triggered_by, signal) {
+            Some(visible) if visible != self.visible => {
+                self.visible = visible;
+                true
+            },
+            _ => false
+        }
+    }
+
+    fn clone_item(&self) -> Box<Item> { Box::new(self.clone()) }
+
+    fn draw(&self, d: &DrawContext) {
+        if self.visible {
+            for (i, tile_id) in self.tiles.iter().enumerate() {
+                let offset_x = i as u32 % self.width;
+                let offset_y = i as u32 / self.width;
+
+                d.tile(self.xy.offset(d.screen, offset_x as f32 * d.tile_size, offset_y as f32 * d.tile_size), *tile_id);
+            }
+        }
+    }
+}
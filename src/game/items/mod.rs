@@ -0,0 +1,748 @@
+use std::any::Any;
+use rustc_serialize::json::Json;
+use super::audio::Sound;
+use super::level::{Level, ObjectWriter, Signal, Tiles, TriggerId};
+use super::wrapping::Screen;
+use super::collision;
+use super::grid::Grid;
+use super::rect::{Point, Rect};
+
+pub mod beanstalk;
+pub mod checkpoint;
+pub mod chest;
+pub mod key;
+pub mod message;
+pub mod monster;
+pub mod switch;
+
+/// An item as the level file places it. Parsers registered in `ObjectTypes` add these to the level.
+pub trait PlacedItem {
+    /// Makes the item as it is when the level starts
+    fn spawn(&self, screen: &Screen, steps_per_second: u32) -> Box<Item>;
+
+    /// The Tiled object that reads back as this item
+    fn export(&self, w: &ObjectWriter) -> Json;
+
+    fn as_any(&self) -> &Any;
+
+    /// Whether `other` is the same kind of item, placed the same way
+    fn equals(&self, other: &PlacedItem) -> bool;
+}
+
+/// `PlacedItem::equals` for kinds that derive PartialEq
+pub fn same_placed_item<T: PlacedItem + PartialEq + 'static>(item: &T, other: &PlacedItem) -> bool {
+    match other.as_any().downcast_ref::<T>() {
+        Some(other) => item == other,
+        None => false
+    }
+}
+
+/// Every item placed in a level, in the order they were read
+pub struct PlacedItems(pub Vec<Box<PlacedItem>>);
+
+impl PlacedItems {
+    pub fn iter(&self) -> ::std::slice::Iter<Box<PlacedItem>> {
+        self.0.iter()
+    }
+}
+
+impl PartialEq for PlacedItems {
+    fn eq(&self, other: &PlacedItems) -> bool {
+        self.0.len() == other.0.len() && self.0.iter().zip(other.0.iter()).all(|(a, b)| a.equals(&**b))
+    }
+}
+
+/// What happens to an item when the player touches it, or doesn't
+pub struct Touch {
+    pub player: Rect<f32>,
+    /// Whether the player's rect overlaps the item's
+    pub touching: bool,
+    /// The player just pressed Down, to open things
+    pub opening: bool,
+    pub alive: bool
+}
+
+/// Something an item wants the game to do
+#[derive(Clone)]
+pub enum ItemEvent {
+    /// Sent to the items, the level and the logic
+    Signal(Signal),
+    /// Gives the player some of one of `ItemTypes`, by name
+    Give(String, u32),
+    /// The player respawns here from now on
+    Checkpoint(Point<f32>),
+    Sound(Sound)
+}
+
+/// Everything an item can affect outside itself
+pub struct ItemContext<'a> {
+    pub screen: &'a Screen,
+    pub effects: &'a mut Effects,
+    pub events: &'a mut Vec<ItemEvent>
+}
+
+impl<'a> ItemContext<'a> {
+    pub fn send(&mut self, event: ItemEvent) {
+        self.events.push(event);
+    }
+
+    /// The cloud items appear and disappear in
+    pub fn poof_around(&mut self, xy: Point<f32>) {
+        let screen = self.screen;
+        self.effects.add_poof(xy.offset(screen, -5.0, -5.0));
+        self.effects.add_poof(xy.offset(screen, 5.0, 5.0));
+        self.effects.add_poof(xy.offset(screen, 12.0, -3.0));
+    }
+}
+
+/// Draws tiles for an item
+pub struct DrawContext<'a> {
+    pub screen: &'a Screen,
+    pub tile_size: f32,
+    draw_tile: &'a (Fn(Point<f32>, u16, (bool, bool), bool) + 'a)
+}
+
+impl<'a> DrawContext<'a> {
+    pub fn new(screen: &'a Screen, tile_size: f32, draw_tile: &'a (Fn(Point<f32>, u16, (bool, bool), bool) + 'a)) -> DrawContext<'a> {
+        DrawContext {
+            screen: screen,
+            tile_size: tile_size,
+            draw_tile: draw_tile
+        }
+    }
+
+    pub fn tile(&self, xy: Point<f32>, id: u16) {
+        self.flipped_tile(xy, id, (false, false));
+    }
+
+    pub fn flipped_tile(&self, xy: Point<f32>, id: u16, flip: (bool, bool)) {
+        (self.draw_tile)(xy, id, flip, false);
+    }
+}
+
+/// An item in the world while the level is played.
+/// Everything but `rect` and `clone_item` does nothing unless the kind says otherwise.
+pub trait Item {
+    /// Where the item is, or None while it's hidden. Hidden items can't be touched, shot or pushed.
+    fn rect(&self) -> Option<Rect<f32>>;
+
+    fn clone_item(&self) -> Box<Item>;
+
+    /// Responds to a trigger turning on or off. Returns true if the item appeared or disappeared.
+    fn signal(&mut self, _signal: Signal, _c: &mut ItemContext) -> bool { false }
+
+    fn step(&mut self, _c: &mut ItemContext) {}
+
+    /// Called while the player is touching the item, and while `is_watching_player` says so
+    fn touch(&mut self, _touch: &Touch, _c: &mut ItemContext) {}
+
+    /// Whether the item needs `touch` even while the player isn't touching it, e.g. to see them step off
+    fn is_watching_player(&self) -> bool { false }
+
+    /// The player reached a checkpoint other than this item
+    fn leave_checkpoint(&mut self) {}
+
+    /// Whether touching the item hurts the player
+    fn is_harmful(&self) -> bool { false }
+
+    /// Whether the player can climb up the item
+    fn is_climbable(&self) -> bool { false }
+
+    /// Whether bullets stop at the item
+    fn is_shootable(&self) -> bool { false }
+
+    fn shot(&mut self, _c: &mut ItemContext) {}
+
+    /// Whether the edge of the screen pushes the item along when it scrolls
+    fn is_pushable(&self) -> bool { false }
+
+    fn move_to(&mut self, _xy: Point<f32>) {}
+
+    /// The item was pushed into a solid tile
+    fn crush(&mut self, _c: &mut ItemContext) {}
+
+    /// Whether checkpoints that restore doors put the item back too
+    fn is_restored_with_doors(&self) -> bool { false }
+
+    fn draw(&self, _d: &DrawContext) {}
+
+    /// Whether the item is drawn over the player
+    fn is_in_front(&self) -> bool { false }
+}
+
+/// Whether `signal` shows (Some(true)) or hides (Some(false)) an item spawned by `triggered_by`
+pub fn spawn_signal(triggered_by: Option<TriggerId>, signal: Signal) -> Option<bool> {
+    match (triggered_by, signal) {
+        (Some(trigger), Signal::On(id)) if trigger == id => Some(true),
+        (Some(trigger), Signal::Off(id)) if trigger == id => Some(false),
+        _ => None
+    }
+}
+
+/// Moves item `i` in the grid to where it is now, if that's changed since it was filed under `filed`
+fn refile(grid: &mut Grid<usize>, filed: &mut Option<Rect<f32>>, item: &Item, i: usize) {
+    let rect = item.rect();
+    if rect == *filed {
+        return;
+    }
+
+    if let Some(ref old) = *filed {
+        grid.remove(old, i);
+    }
+    if let Some(ref new) = rect {
+        grid.insert(new, i);
+    }
+    *filed = rect;
+}
+
+pub fn tile_from_phase(tiles: &[u16], phase: f32) -> u16 {
+    let i = phase * tiles.len() as f32;
+    let tile_index = if i < 0.0 { 0 } else if i >= tiles.len() as f32 { tiles.len() - 1 } else { i as usize };
+
+    tiles[tile_index]
+}
+
+#[derive(Clone)]
+pub struct Poof {
+    pub xy: Point<f32>,
+    pub phase: f32
+}
+
+#[derive(Clone, Copy)]
+pub struct Bullet {
+    pub xy: Point<f32>,
+    pub vel_x: f32,
+    pub phase: f32,
+    timeout: u32,
+    tile_size: f32
+}
+
+impl Bullet {
+    pub fn get_rect(&self) -> Rect<f32> {
+        Rect::new(self.xy, (self.tile_size, 1.0))
+    }
+
+    /// Where the bullet was at the start of the last step, and how far it's moved since
+    fn get_path(&self, screen: &Screen) -> (Rect<f32>, (f32, f32)) {
+        (self.get_rect().offset(screen, -self.vel_x, 0.0), (self.vel_x, 0.0))
+    }
+}
+
+/// Something a bullet can hit
+#[derive(Copy, Clone, PartialEq)]
+enum BulletTarget {
+    /// By index in `DynamicItems::items`
+    Item(usize),
+    Tile
+}
+
+#[derive(Clone)]
+pub struct Useless {
+    pub xy: Point<f32>,
+    pub phase: f32
+}
+
+/// Things that only live for a moment and don't belong to any item
+#[derive(Clone)]
+pub struct Effects {
+    pub poofs: Vec<Poof>,
+    pub bullets: Vec<Bullet>,
+    pub useless: Vec<Useless>
+}
+
+impl Effects {
+    fn new() -> Effects {
+        Effects {
+            poofs: Vec::new(),
+            bullets: Vec::new(),
+            useless: Vec::new()
+        }
+    }
+
+    pub fn add_poof(&mut self, xy: Point<f32>) {
+        self.poofs.push(Poof {
+            xy: xy,
+            phase: 0.0
+        });
+    }
+
+    pub fn add_useless_points(&mut self, xy: Point<f32>) {
+        self.useless.push(Useless {
+            xy: xy,
+            phase: 0.0
+        });
+    }
+
+    fn step_poofs(&mut self) {
+        let new_poofs = self.poofs.iter().filter_map(|poof| {
+            let phase = poof.phase + 0.05;
+            if phase >= 1.0 {
+                None
+            } else {
+                Some(Poof {
+                    xy: poof.xy,
+                    phase: phase
+                })
+            }
+        }).collect();
+
+        self.poofs = new_poofs;
+    }
+
+    fn step_bullets(&mut self, screen: &Screen) {
+        let new_bullets = self.bullets.iter().filter_map(|bullet| {
+            let mut phase = bullet.phase + 0.3;
+            if phase >= 1.0 { phase = 1.0; }
+
+            let new_xy = bullet.xy.offset(screen, bullet.vel_x, 0.0);
+
+            if bullet.timeout - 1 == 0 {
+                None
+            } else {
+                Some(Bullet {
+                    xy: new_xy,
+                    vel_x: bullet.vel_x,
+                    phase: phase,
+                    timeout: bullet.timeout - 1,
+                    tile_size: bullet.tile_size
+                })
+            }
+        }).collect();
+
+        self.bullets = new_bullets;
+    }
+
+    fn step_useless(&mut self, screen: &Screen) {
+        let new_useless = self.useless.iter().filter_map(|useless| {
+            let phase = useless.phase + 0.03;
+            if phase >= 1.0 {
+                None
+            } else {
+                Some(Useless {
+                    xy: useless.xy.offset(screen, 0.0, -0.5),
+                    phase: phase
+                })
+            }
+        }).collect();
+
+        self.useless = new_useless;
+    }
+
+    fn step(&mut self, screen: &Screen) {
+        self.step_poofs();
+        self.step_bullets(screen);
+        self.step_useless(screen);
+    }
+}
+
+pub struct DynamicItems {
+    pub effects: Effects,
+    items: Vec<Box<Item>>,
+
+    screen: Screen,
+    /// Where the items are, by index in `items`
+    grid: Grid<usize>,
+    /// The rect each item is in the grid under, by index in `items`
+    filed: Vec<Option<Rect<f32>>>,
+    /// The items that were watching the player after the last call to `touch`
+    watching: Vec<usize>,
+    /// Every signal sent since the last call to `take_signals`
+    signals: Vec<Signal>,
+    /// Every event the items sent since the last call to `take_events`
+    events: Vec<ItemEvent>
+}
+
+impl Clone for DynamicItems {
+    fn clone(&self) -> DynamicItems {
+        DynamicItems {
+            effects: self.effects.clone(),
+            items: self.items.iter().map(|item| item.clone_item()).collect(),
+            screen: self.screen,
+            grid: self.grid.clone(),
+            filed: self.filed.clone(),
+            watching: self.watching.clone(),
+            signals: self.signals.clone(),
+            events: self.events.clone()
+        }
+    }
+}
+
+impl DynamicItems {
+    pub fn new(level: &Level, steps_per_second: u32) -> DynamicItems {
+        let screen = level.get_screen();
+        let items = level.items.iter().map(|item| item.spawn(&screen, steps_per_second)).collect();
+
+        DynamicItems::with_items(screen, items)
+    }
+
+    fn with_items(screen: Screen, items: Vec<Box<Item>>) -> DynamicItems {
+        let mut grid = Grid::new(&screen);
+        let filed: Vec<Option<Rect<f32>>> = items.iter().map(|item| item.rect()).collect();
+
+        for (i, rect) in filed.iter().enumerate() {
+            if let Some(ref rect) = *rect {
+                grid.insert(rect, i);
+            }
+        }
+
+        DynamicItems {
+            effects: Effects::new(),
+            items: items,
+            screen: screen,
+            grid: grid,
+            filed: filed,
+            watching: Vec::new(),
+            signals: Vec::new(),
+            events: Vec::new()
+        }
+    }
+
+    /// The items that `rect` overlaps, by index
+    fn touching(&self, rect: &Rect<f32>) -> Vec<usize> {
+        self.grid.query(rect).into_iter().filter(|&i| {
+            match self.items[i].rect() {
+                Some(item_rect) => collision::test_rects(&self.screen, rect, &item_rect),
+                None => false
+            }
+        }).collect()
+    }
+
+    pub fn signal(&mut self, signal: Signal) -> bool {
+        let mut did_something = false;
+
+        self.signals.push(signal);
+
+        {
+            let mut c = ItemContext {
+                screen: &self.screen,
+                effects: &mut self.effects,
+                events: &mut self.events
+            };
+            for (i, item) in self.items.iter_mut().enumerate() {
+                did_something |= item.signal(signal, &mut c);
+                // Spawned items go back to where they started
+                refile(&mut self.grid, &mut self.filed[i], &**item, i);
+            }
+        }
+
+        did_something
+    }
+
+    pub fn take_signals(&mut self) -> Vec<Signal> {
+        ::std::mem::replace(&mut self.signals, Vec::new())
+    }
+
+    pub fn take_events(&mut self) -> Vec<ItemEvent> {
+        ::std::mem::replace(&mut self.events, Vec::new())
+    }
+
+    /// Lets the items near the player, and the ones watching for them, see where they are.
+    /// `opening` is true if they just pressed Down.
+    pub fn touch(&mut self, rect: &Rect<f32>, opening: bool, alive: bool) {
+        let nearby = self.touching(rect);
+        let mut told = nearby.clone();
+        for &i in self.watching.iter() {
+            if !told.contains(&i) {
+                told.push(i);
+            }
+        }
+        told.sort();
+
+        let mut watching = Vec::new();
+        let mut reached = None;
+
+        {
+            let mut c = ItemContext {
+                screen: &self.screen,
+                effects: &mut self.effects,
+                events: &mut self.events
+            };
+            for &i in told.iter() {
+                let touch = Touch {
+                    player: *rect,
+                    touching: nearby.contains(&i),
+                    opening: opening,
+                    alive: alive
+                };
+
+                let item = &mut self.items[i];
+                let sent = c.events.len();
+                item.touch(&touch, &mut c);
+                if c.events[sent..].iter().any(|e| match *e { ItemEvent::Checkpoint(_) => true, _ => false }) {
+                    reached = Some(i);
+                }
+                if item.is_watching_player() {
+                    watching.push(i);
+                }
+                refile(&mut self.grid, &mut self.filed[i], &**item, i);
+            }
+        }
+
+        self.watching = watching;
+
+        // Only the last checkpoint reached counts
+        if let Some(reached) = reached {
+            for (i, item) in self.items.iter_mut().enumerate() {
+                if i != reached {
+                    item.leave_checkpoint();
+                }
+            }
+        }
+    }
+
+    /// Whether `rect` touches anything harmful
+    pub fn hurts(&self, rect: &Rect<f32>) -> bool {
+        self.touching(rect).into_iter().any(|i| self.items[i].is_harmful())
+    }
+
+    /// The rect of something climbable that `rect` touches
+    pub fn climbable_at(&self, rect: &Rect<f32>) -> Option<Rect<f32>> {
+        self.touching(rect).into_iter().find(|&i| self.items[i].is_climbable()).and_then(|i| self.items[i].rect())
+    }
+
+    /// Copies of the items that checkpoints restoring doors put back, by index
+    pub fn save_door_items(&self) -> Vec<(usize, Box<Item>)> {
+        self.items.iter().enumerate().filter(|&(_, item)| item.is_restored_with_doors()).map(|(i, item)| (i, item.clone_item())).collect()
+    }
+
+    /// Puts back items saved by `save_door_items` from these same items
+    pub fn restore_door_items(&mut self, saved: &[(usize, Box<Item>)]) {
+        for &(i, ref item) in saved.iter() {
+            self.items[i] = item.clone_item();
+            refile(&mut self.grid, &mut self.filed[i], &*self.items[i], i);
+        }
+    }
+
+    pub fn add_poof(&mut self, xy: Point<f32>) {
+        self.effects.add_poof(xy);
+    }
+
+    pub fn add_bullet(&mut self, xy: Point<f32>, vel_x: f32) {
+        self.effects.bullets.push(Bullet {
+            xy: xy,
+            vel_x: vel_x,
+            phase: 0.0,
+            timeout: 40,
+            tile_size: self.screen.tile_size
+        });
+    }
+
+    /// Checks the path each bullet took during the last step, so fast bullets can't pass through things.
+    /// The bullet and the first thing along its path annihilate each other.
+    pub fn bullet_item_collision(&mut self, tiles: &Tiles) {
+        let screen = self.screen;
+        let bullets = ::std::mem::replace(&mut self.effects.bullets, Vec::new());
+        let mut new_bullets = Vec::new();
+
+        for bullet in bullets.iter() {
+            let (rect, delta) = bullet.get_path(&screen);
+
+            let mut hits: Vec<(f32, BulletTarget)> = Vec::new();
+
+            // Only items near the bullet's path can be hit
+            for i in self.grid.query(&collision::sweep_bounds(&screen, &rect, delta)) {
+                let item = &self.items[i];
+                let target_rect = match item.rect() {
+                    Some(target_rect) if item.is_shootable() => target_rect,
+                    _ => continue
+                };
+                if let Some(t) = collision::sweep_rects(&screen, &rect, delta, &target_rect) {
+                    hits.push((t, BulletTarget::Item(i)));
+                }
+            }
+            if let Some(t) = tiles.sweep(&rect, delta) {
+                hits.push((t, BulletTarget::Tile));
+            }
+
+            // Everything hit at the same moment as the first hit goes
+            let first = hits.iter().fold(None, |first: Option<f32>, &(t, _)| match first {
+                Some(f) if f <= t => Some(f),
+                _ => Some(t)
+            });
+
+            let first = match first {
+                Some(first) => first,
+                None => {
+                    new_bullets.push(bullet.clone());
+                    continue;
+                }
+            };
+
+            for &(t, target) in hits.iter().filter(|&&(t, _)| t == first) {
+                match target {
+                    BulletTarget::Item(i) => {
+                        let mut c = ItemContext {
+                            screen: &screen,
+                            effects: &mut self.effects,
+                            events: &mut self.events
+                        };
+                        self.items[i].shot(&mut c);
+                        refile(&mut self.grid, &mut self.filed[i], &*self.items[i], i);
+                    },
+                    BulletTarget::Tile => {
+                        let (dx, dy) = delta;
+                        self.effects.add_poof(rect.left_top().offset(&screen, dx * t - 8.0, dy * t - 8.0));
+                    }
+                }
+            }
+        }
+
+        self.effects.bullets = new_bullets;
+    }
+
+    /// Returns (true, _) if items have been moved.
+    /// Returns (_, true) if items have been destroyed.
+    pub fn adjust_to_scroll_boundary(&mut self, screen: &Screen, tiles: &Tiles, x_line: f32, x_inc: bool, x_dec: bool) -> (bool, bool) {
+        let width = screen.width;
+
+        let do_collision = |rect: &Rect<f32>| -> (Rect<f32>, bool, bool) {
+            let mut moved = false;
+
+            let new_rect = if x_inc {
+                if collision::test_rect_vert_line(screen, rect, x_line) {
+                    moved = true;
+                    rect.set_x(screen, x_line)
+                } else { *rect }
+            } else if x_dec {
+                if collision::test_rect_vert_line(screen, rect, x_line) {
+                    moved = true;
+                    rect.set_x(screen, (x_line - rect.width() + width) % width)
+                } else { *rect}
+            } else { *rect };
+
+            let destroy = if let Some((_, _)) = tiles.collision_tile(&new_rect, (None, None)) { true }
+            else { false };
+
+            (new_rect, moved, destroy)
+        };
+
+        // Item sliding and crushing occurs here
+        let mut moved = false;
+        let mut destroyed = false;
+
+        // Only items the line goes through get pushed
+        let line = Rect::from_xywh(screen, x_line - 1.0, 0.0, 2.0, screen.height);
+        let mut nearby = self.grid.query(&line);
+        nearby.sort();
+
+        {
+            let mut c = ItemContext {
+                screen: screen,
+                effects: &mut self.effects,
+                events: &mut self.events
+            };
+
+            for &i in nearby.iter() {
+                let item = &mut self.items[i];
+                let rect = match item.rect() {
+                    Some(rect) if item.is_pushable() => rect,
+                    _ => continue
+                };
+                let (new_rect, mov, destroy) = do_collision(&rect);
+
+                if !mov { continue }
+
+                moved = true;
+                item.move_to(new_rect.left_top());
+
+                if destroy {
+                    item.crush(&mut c);
+                    destroyed = true;
+                }
+                refile(&mut self.grid, &mut self.filed[i], &**item, i);
+            }
+        }
+
+        (moved, destroyed)
+    }
+
+    pub fn step(&mut self, screen: &Screen) {
+        self.effects.step(screen);
+
+        {
+            let mut c = ItemContext {
+                screen: screen,
+                effects: &mut self.effects,
+                events: &mut self.events
+            };
+            for (i, item) in self.items.iter_mut().enumerate() {
+                item.step(&mut c);
+                refile(&mut self.grid, &mut self.filed[i], &**item, i);
+            }
+        }
+    }
+
+    /// Draws the items that go behind the player, or the ones that go in front
+    pub fn draw(&self, d: &DrawContext, in_front: bool) {
+        for item in self.items.iter().filter(|item| item.is_in_front() == in_front) {
+            item.draw(d);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use super::{DynamicItems, Item, ItemContext, Touch};
+    use super::super::collision;
+    use super::super::rect::{Point, Rect};
+    use super::super::wrapping::Screen;
+
+    /// Moves right a pixel every step if it's `moving`, and counts how often it's touched
+    #[derive(Clone)]
+    struct Block {
+        xy: Point<f32>,
+        moving: bool,
+        touches: Rc<Cell<u32>>
+    }
+
+    impl Item for Block {
+        fn rect(&self) -> Option<Rect<f32>> {
+            Some(Rect::new(self.xy, (16.0, 16.0)))
+        }
+
+        fn clone_item(&self) -> Box<Item> { Box::new(self.clone()) }
+
+        fn step(&mut self, c: &mut ItemContext) {
+            if self.moving {
+                self.xy = self.xy.offset(c.screen, 1.0, 0.0);
+            }
+        }
+
+        fn touch(&mut self, _touch: &Touch, _c: &mut ItemContext) {
+            self.touches.set(self.touches.get() + 1);
+        }
+    }
+
+    #[test]
+    fn many_items() {
+        let screen = Screen::new(1600.0, 1600.0, 16.0);
+        let touches = Rc::new(Cell::new(0));
+
+        // One in every tile, and every other one moving
+        let blocks: Vec<Box<Item>> = (0..10000).map(|i| {
+            let xy = Point::new(&screen, ((i % 100) as f32 * 16.0, (i / 100) as f32 * 16.0));
+            Box::new(Block { xy: xy, moving: i % 2 == 0, touches: touches.clone() }) as Box<Item>
+        }).collect();
+        let mut items = DynamicItems::with_items(screen, blocks);
+
+        for _ in 0..20 {
+            items.step(&screen);
+        }
+
+        // The grid kept up with the blocks that moved
+        let player = Rect::from_xywh(&screen, 90.0, 100.0, 8.0, 8.0);
+        let expected: Vec<usize> = (0..items.items.len()).filter(|&i| {
+            collision::test_rects(&screen, &player, &items.items[i].rect().unwrap())
+        }).collect();
+        let mut found = items.touching(&player);
+        found.sort();
+        assert!(found == expected);
+        assert!(expected == vec![604, 605]);
+
+        // Only the blocks the player touches are told
+        items.touch(&player, false, true);
+        assert!(touches.get() == 2);
+    }
+}
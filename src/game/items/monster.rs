@@ -0,0 +1,233 @@
+use std::any::Any;
+use rustc_serialize::json::{Json, Object};
+use super::{spawn_signal, same_placed_item, tile_from_phase, DrawContext, Item, ItemContext, PlacedItem};
+use super::super::level::{LevelError, LevelObjects, ObjectContext, ObjectWriter, Signal, TriggerId};
+use super::super::rect::{Point, Rect};
+use super::super::wrapping::Screen;
+
+/// Stays where it's put
+#[derive(PartialEq)]
+pub struct PlacedMonster1 {
+    pub x: f32,
+    pub y: f32,
+    pub triggered_by: Option<TriggerId>
+}
+
+/// Paces back and forth
+#[derive(PartialEq)]
+pub struct PlacedMonster2 {
+    pub x: f32,
+    pub y: f32,
+    pub triggered_by: Option<TriggerId>
+}
+
+pub fn parse_monster1(o: &ObjectContext, objects: &mut LevelObjects) -> Result<(), LevelError> {
+    let triggered_by = try!(o.trigger("triggered_by"));
+
+    objects.items.push(Box::new(PlacedMonster1 {
+        x: o.x,
+        y: o.y,
+        triggered_by: triggered_by
+    }));
+
+    Ok(())
+}
+
+pub fn parse_monster2(o: &ObjectContext, objects: &mut LevelObjects) -> Result<(), LevelError> {
+    let triggered_by = try!(o.trigger("triggered_by"));
+
+    objects.items.push(Box::new(PlacedMonster2 {
+        x: o.x,
+        y: o.y,
+        triggered_by: triggered_by
+    }));
+
+    Ok(())
+}
+
+impl PlacedItem for PlacedMonster1 {
+    fn spawn(&self, screen: &Screen, _steps_per_second: u32) -> Box<Item> {
+        Box::new(Monster1 {
+            xy: Point::new(screen, (self.x, self.y)),
+            visible: false,
+            triggered_by: self.triggered_by,
+            phase: 0.0,
+            tile_size: screen.tile_size
+        })
+    }
+
+    fn export(&self, w: &ObjectWriter) -> Json {
+        let mut p = Object::new();
+        w.set_optional_trigger(&mut p, "triggered_by", self.triggered_by);
+        w.object("monster1", self.x, self.y, w.tile_size, w.tile_size, p)
+    }
+
+    fn as_any(&self) -> &Any { self }
+
+    fn equals(&self, other: &PlacedItem) -> bool { same_placed_item(self, other) }
+}
+
+impl PlacedItem for PlacedMonster2 {
+    fn spawn(&self, screen: &Screen, _steps_per_second: u32) -> Box<Item> {
+        Box::new(Monster2 {
+            original_xy: Point::new(screen, (self.x, self.y)),
+            xy: Point::new(screen, (self.x, self.y)),
+            visible: false,
+            triggered_by: self.triggered_by,
+            phase: 0.0,
+            move_phase: 0.0,
+            tile_size: screen.tile_size
+        })
+    }
+
+    fn export(&self, w: &ObjectWriter) -> Json {
+        let mut p = Object::new();
+        w.set_optional_trigger(&mut p, "triggered_by", self.triggered_by);
+        w.object("monster2", self.x, self.y, w.tile_size, w.tile_size, p)
+    }
+
+    fn as_any(&self) -> &Any { self }
+
+    fn equals(&self, other: &PlacedItem) -> bool { same_placed_item(self, other) }
+}
+
+#[derive(Clone)]
+pub struct Monster1 {
+    pub xy: Point<f32>,
+    pub visible: bool,
+    pub phase: f32,
+    triggered_by: Option<TriggerId>,
+    tile_size: f32
+}
+
+impl Monster1 {
+    pub fn spawn(&mut self) {
+        self.visible = true;
+    }
+}
+
+impl Item for Monster1 {
+    fn rect(&self) -> Option<Rect<f32>> {
+        if self.visible { Some(Rect::new(self.xy, (self.tile_size, self.tile_size))) } else { None }
+    }
+
+    fn clone_item(&self) -> Box<Item> { Box::new(self.clone()) }
+
+    fn signal(&mut self, signal: Signal, c: &mut ItemContext) -> bool {
+        match spawn_signal(self.triggered_by, signal) {
+            Some(true) if !self.visible => self.spawn(),
+            Some(false) if self.visible => self.visible = false,
+            _ => return false
+        }
+        c.poof_around(self.xy);
+        true
+    }
+
+    fn step(&mut self, _c: &mut ItemContext) {
+        if self.visible {
+            self.phase = (self.phase + 0.015) % 1.0;
+        }
+    }
+
+    fn is_harmful(&self) -> bool { true }
+
+    fn is_shootable(&self) -> bool { true }
+
+    fn shot(&mut self, c: &mut ItemContext) {
+        c.effects.add_poof(self.xy);
+        self.visible = false;
+    }
+
+    fn is_pushable(&self) -> bool { true }
+
+    fn move_to(&mut self, xy: Point<f32>) {
+        self.xy = xy;
+    }
+
+    fn crush(&mut self, c: &mut ItemContext) {
+        self.visible = false;
+        c.effects.add_poof(self.xy);
+    }
+
+    fn draw(&self, d: &DrawContext) {
+        if self.visible {
+            d.tile(self.xy, tile_from_phase(&[0x26, 0x27], self.phase));
+        }
+    }
+
+    fn is_in_front(&self) -> bool { true }
+}
+
+#[derive(Clone)]
+pub struct Monster2 {
+    pub original_xy: Point<f32>,
+    pub xy: Point<f32>,
+    pub visible: bool,
+    pub phase: f32,
+    move_phase: f32,
+    triggered_by: Option<TriggerId>,
+    tile_size: f32
+}
+
+impl Monster2 {
+    pub fn spawn(&mut self) {
+        self.visible = true;
+        self.xy = self.original_xy;
+    }
+}
+
+impl Item for Monster2 {
+    fn rect(&self) -> Option<Rect<f32>> {
+        if self.visible { Some(Rect::new(self.xy, (self.tile_size, self.tile_size))) } else { None }
+    }
+
+    fn clone_item(&self) -> Box<Item> { Box::new(self.clone()) }
+
+    fn signal(&mut self, signal: Signal, c: &mut ItemContext) -> bool {
+        match spawn_signal(self.triggered_by, signal) {
+            Some(true) if !self.visible => self.spawn(),
+            Some(false) if self.visible => self.visible = false,
+            _ => return false
+        }
+        c.poof_around(self.xy);
+        true
+    }
+
+    fn step(&mut self, c: &mut ItemContext) {
+        fn lerp(a: f32, b: f32, p: f32) -> f32 { (b-a)*p + a }
+
+        if !self.visible {
+            return;
+        }
+
+        self.phase = (self.phase + 0.015) % 1.0;
+        self.move_phase = (self.move_phase + 0.005) % 1.0;
+
+        let p = match self.move_phase*2.0 {
+            e @ 0.0...1.0 => e,
+            e @ 1.0...2.0 => 1.0-(e-1.0),
+            _ => 0.0
+        };
+
+        let x = self.original_xy.x();
+        let new_x = lerp(x - self.tile_size, x + self.tile_size, p);
+        self.xy = self.xy.set_x(c.screen, new_x);
+    }
+
+    fn is_harmful(&self) -> bool { true }
+
+    fn is_shootable(&self) -> bool { true }
+
+    fn shot(&mut self, c: &mut ItemContext) {
+        c.effects.add_poof(self.xy);
+        self.visible = false;
+    }
+
+    fn draw(&self, d: &DrawContext) {
+        if self.visible {
+            d.tile(self.xy, tile_from_phase(&[0x28, 0x29], self.phase));
+        }
+    }
+
+    fn is_in_front(&self) -> bool { true }
+}
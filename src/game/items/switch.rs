@@ -0,0 +1,204 @@
+use std::any::Any;
+use rustc_serialize::json::{Json, Object};
+use super::{spawn_signal, same_placed_item, DrawContext, Item, ItemContext, ItemEvent, PlacedItem, Touch};
+use super::super::level::{LevelError, LevelObjects, ObjectContext, ObjectWriter, Signal, TriggerId};
+use super::super::rect::{Point, Rect};
+use super::super::wrapping::Screen;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum SwitchMode {
+    /// Stays down once pressed
+    Latch,
+    /// Released as soon as the player steps off
+    Momentary,
+    /// Released a number of seconds after the player steps off
+    Timed(f32),
+    /// Flips between on and off each time the player steps on
+    Toggle
+}
+
+#[derive(PartialEq)]
+pub struct PlacedSwitch {
+    pub x: f32,
+    pub y: f32,
+    pub trigger: TriggerId,
+    pub triggered_by: Option<TriggerId>,
+    pub mode: SwitchMode
+}
+
+pub fn parse(o: &ObjectContext, objects: &mut LevelObjects) -> Result<(), LevelError> {
+    let trigger = try!(o.require("trigger", o.trigger("trigger")));
+    let triggered_by = try!(o.trigger("triggered_by"));
+    let mode = match try!(o.string("mode")) {
+        None => SwitchMode::Latch,
+        Some(name) => match &name as &str {
+            "latch" => SwitchMode::Latch,
+            "momentary" => SwitchMode::Momentary,
+            "timed" => SwitchMode::Timed(try!(o.require("timeout", o.number("timeout")))),
+            "toggle" => SwitchMode::Toggle,
+            _ => return Err(LevelError::InvalidProperty(o.location.clone(), "mode".to_string(), "latch, momentary, timed or toggle"))
+        }
+    };
+
+    objects.items.push(Box::new(PlacedSwitch {
+        x: o.x,
+        y: o.y,
+        trigger: trigger,
+        triggered_by: triggered_by,
+        mode: mode
+    }));
+
+    Ok(())
+}
+
+impl PlacedItem for PlacedSwitch {
+    fn spawn(&self, screen: &Screen, steps_per_second: u32) -> Box<Item> {
+        Box::new(Switch {
+            trigger: self.trigger,
+            triggered_by: self.triggered_by,
+            visible: self.triggered_by.is_none(),
+            xy: Point::new(screen, (self.x, self.y)),
+            is_down: false,
+            mode: self.mode,
+            occupied: false,
+            pressed: false,
+            release_steps: match self.mode {
+                SwitchMode::Timed(seconds) => (seconds * steps_per_second as f32) as u32,
+                _ => 0
+            },
+            release_timeout: 0,
+            tile_size: screen.tile_size
+        })
+    }
+
+    fn export(&self, w: &ObjectWriter) -> Json {
+        let mut p = Object::new();
+        w.set_trigger(&mut p, "trigger", self.trigger);
+        w.set_optional_trigger(&mut p, "triggered_by", self.triggered_by);
+        match self.mode {
+            SwitchMode::Latch => (),
+            SwitchMode::Momentary => w.set(&mut p, "mode", "momentary".to_string()),
+            SwitchMode::Timed(timeout) => {
+                w.set(&mut p, "mode", "timed".to_string());
+                w.set(&mut p, "timeout", timeout.to_string());
+            },
+            SwitchMode::Toggle => w.set(&mut p, "mode", "toggle".to_string())
+        }
+        w.object("switch", self.x, self.y, w.tile_size, w.tile_size, p)
+    }
+
+    fn as_any(&self) -> &Any { self }
+
+    fn equals(&self, other: &PlacedItem) -> bool { same_placed_item(self, other) }
+}
+
+#[derive(Clone)]
+pub struct Switch {
+    pub trigger: TriggerId,
+    pub triggered_by: Option<TriggerId>,
+    pub xy: Point<f32>,
+    pub is_down: bool,
+    pub visible: bool,
+    mode: SwitchMode,
+    /// Whether the player was on the switch during the last step
+    occupied: bool,
+    /// For momentary and timed switches: whether the player is what's holding the switch down.
+    /// Triggers can press the switch too, and the player stepping off doesn't release those.
+    pressed: bool,
+    /// For timed switches: how many steps the switch stays down after the player steps off
+    release_steps: u32,
+    release_timeout: u32,
+    tile_size: f32
+}
+
+impl Item for Switch {
+    fn rect(&self) -> Option<Rect<f32>> {
+        if self.visible { Some(Rect::new(self.xy, (self.tile_size, self.tile_size))) } else { None }
+    }
+
+    fn clone_item(&self) -> Box<Item> { Box::new(self.clone()) }
+
+    fn signal(&mut self, signal: Signal, c: &mut ItemContext) -> bool {
+        match signal {
+            Signal::On(id) if id == self.trigger => self.is_down = true,
+            Signal::Off(id) if id == self.trigger => self.is_down = false,
+            _ => ()
+        }
+
+        match spawn_signal(self.triggered_by, signal) {
+            Some(visible) if visible != self.visible => {
+                self.visible = visible;
+                c.poof_around(self.xy);
+                true
+            },
+            _ => false
+        }
+    }
+
+    /// Presses and releases the switch, and sends its trigger when it changes
+    fn touch(&mut self, touch: &Touch, c: &mut ItemContext) {
+        // Switches love triggers
+        if !self.visible {
+            return;
+        }
+
+        let hit = touch.touching;
+        let stepped_on = hit && !self.occupied;
+        self.occupied = hit;
+
+        let was_down = self.is_down;
+
+        match self.mode {
+            SwitchMode::Latch => {
+                if hit { self.is_down = true; }
+            },
+            SwitchMode::Momentary => {
+                if hit {
+                    self.is_down = true;
+                    self.pressed = true;
+                } else if self.pressed {
+                    self.is_down = false;
+                    self.pressed = false;
+                }
+            },
+            SwitchMode::Timed(_) => {
+                if hit {
+                    self.is_down = true;
+                    self.pressed = true;
+                    self.release_timeout = self.release_steps;
+                } else if self.pressed {
+                    if self.release_timeout == 0 {
+                        self.is_down = false;
+                        self.pressed = false;
+                    } else {
+                        self.release_timeout -= 1;
+                    }
+                }
+            },
+            SwitchMode::Toggle => {
+                if stepped_on { self.is_down = !self.is_down; }
+            }
+        }
+
+        match (was_down, self.is_down) {
+            (false, true) => c.send(ItemEvent::Signal(Signal::On(self.trigger))),
+            (true, false) => c.send(ItemEvent::Signal(Signal::Off(self.trigger))),
+            _ => ()
+        }
+    }
+
+    /// Until the player has stepped off, and anything they're holding down is released
+    fn is_watching_player(&self) -> bool {
+        self.occupied || self.pressed
+    }
+
+    fn draw(&self, d: &DrawContext) {
+        if self.visible {
+            let tile = match self.is_down {
+                false => 0x18,
+                true => 0x19
+            };
+            d.tile(self.xy, tile);
+        }
+    }
+}
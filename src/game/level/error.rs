@@ -0,0 +1,78 @@
+use std::fmt;
+
+/// Where in the map a problem was found
+#[derive(Clone, Debug)]
+pub enum Location {
+    Map,
+    Layer(String),
+    /// A tile in a tileset, by its local ID
+    Tile {
+        tileset: String,
+        id: u32
+    },
+    Object {
+        layer: String,
+        index: usize,
+        typ: String
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &Location::Map => write!(f, "map"),
+            &Location::Layer(ref name) => write!(f, "layer '{}'", name),
+            &Location::Tile { ref tileset, id } => write!(f, "tileset '{}', tile #{}", tileset, id),
+            &Location::Object { ref layer, index, ref typ } => {
+                if typ.is_empty() {
+                    write!(f, "layer '{}', object #{}", layer, index)
+                } else {
+                    write!(f, "layer '{}', object #{} ({})", layer, index, typ)
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum LevelError {
+    /// The level file couldn't be read
+    Io(String),
+    /// The file isn't valid JSON or XML
+    Syntax(String),
+    /// A required attribute or layer is absent
+    MissingField(Location, &'static str),
+    /// An attribute is present, but unusable. The last value describes what's wrong.
+    InvalidField(Location, &'static str, String),
+    /// A required custom property is absent
+    MissingProperty(Location, String),
+    /// A custom property can't be read. The last value is what it was expected to be.
+    InvalidProperty(Location, String, &'static str),
+    UnknownObjectType(Location),
+    /// (location, property, trigger name, what's wrong)
+    InvalidTrigger(Location, String, String, &'static str),
+    /// (location, expected, found)
+    WrongTileCount(Location, usize, usize),
+    /// A global tile ID that none of the map's tilesets cover
+    UnknownTile(Location, u32),
+    /// The level can't be saved in the format the file's extension asks for
+    UnsupportedFormat(String)
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &LevelError::Io(ref e) => write!(f, "{}", e),
+            &LevelError::Syntax(ref e) => write!(f, "Syntax error: {}", e),
+            &LevelError::MissingField(ref loc, field) => write!(f, "{}: missing '{}'", loc, field),
+            &LevelError::InvalidField(ref loc, field, ref why) => write!(f, "{}: '{}' {}", loc, field, why),
+            &LevelError::MissingProperty(ref loc, ref property) => write!(f, "{}: requires property '{}'", loc, property),
+            &LevelError::InvalidProperty(ref loc, ref property, expected) => write!(f, "{}: property '{}' is not {}", loc, property, expected),
+            &LevelError::UnknownObjectType(ref loc) => write!(f, "{}: unknown object type", loc),
+            &LevelError::InvalidTrigger(ref loc, ref property, ref name, why) => write!(f, "{}: trigger '{}' in property '{}' {}", loc, name, property, why),
+            &LevelError::WrongTileCount(ref loc, expected, found) => write!(f, "{}: expected {} tiles, found {}", loc, expected, found),
+            &LevelError::UnknownTile(ref loc, gid) => write!(f, "{}: tile {} isn't in any of the tilesets", loc, gid),
+            &LevelError::UnsupportedFormat(ref path) => write!(f, "Can't save {}: levels can only be saved as Tiled JSON (.json)", path)
+        }
+    }
+}
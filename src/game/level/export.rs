@@ -0,0 +1,384 @@
+use rustc_serialize::json::{Json, Object};
+use super::{Level, CheckpointRestore, GateKind, ParallaxSource, Tile, TileKind, TileLayer, TileType, Tileset, TriggerId, GAME_TILESET_IMAGE, PHYSICS_PROPERTY_PREFIX};
+use super::properties;
+use super::triggers::TriggerNames;
+
+// The game's own tileset, written for maps that don't declare any. It's 8 tiles wide and 9 tiles tall.
+static TILESET_NAME: &'static str = "tileset";
+static TILESET_COLUMNS: u32 = 8;
+static TILESET_ROWS: u32 = 9;
+
+static OBJECT_LAYER_NAME: &'static str = "Object Layer 1";
+
+/// Writes the Tiled objects that placed items are read back from
+pub struct ObjectWriter<'a> {
+    pub tile_size: f32,
+    names: &'a TriggerNames
+}
+
+impl<'a> ObjectWriter<'a> {
+    pub fn object(&self, typ: &str, x: f32, y: f32, width: f32, height: f32, properties: Object) -> Json {
+        object(typ, x, y, width, height, properties)
+    }
+
+    pub fn set(&self, properties: &mut Object, key: &str, value: String) {
+        set(properties, key, value);
+    }
+
+    pub fn set_trigger(&self, properties: &mut Object, key: &str, trigger: TriggerId) {
+        set_trigger(properties, key, trigger, self.names);
+    }
+
+    pub fn set_optional_trigger(&self, properties: &mut Object, key: &str, trigger: Option<TriggerId>) {
+        set_optional_trigger(properties, key, trigger, self.names);
+    }
+}
+
+/// Turns a level back into a Tiled JSON map that `parse_from_json` can read
+pub fn to_json(level: &Level) -> Json {
+    let tile_size = level.tile_size;
+    let names = &level.trigger_names;
+
+    // Parallax layers are always drawn behind the rest
+    let mut layers: Vec<Json> = level.parallax_layers.iter().map(|parallax| {
+        let (rate_x, rate_y) = parallax.rate;
+
+        let mut layer = match parallax.source {
+            ParallaxSource::Layer(ref tile_layer) => tile_layer_json(level, tile_layer, Object::new()),
+            ParallaxSource::Tile(ref tile) => {
+                let mut p = Object::new();
+                set(&mut p, "tile", tile_gid(tile).to_string());
+
+                let mut image_layer = Object::new();
+                image_layer.insert("name".to_string(), Json::String(parallax.name.clone()));
+                image_layer.insert("type".to_string(), Json::String("imagelayer".to_string()));
+                image_layer.insert("image".to_string(), Json::String(GAME_TILESET_IMAGE.to_string()));
+                image_layer.insert("x".to_string(), Json::U64(0));
+                image_layer.insert("y".to_string(), Json::U64(0));
+                image_layer.insert("opacity".to_string(), Json::U64(1));
+                image_layer.insert("visible".to_string(), Json::Boolean(true));
+                image_layer.insert("properties".to_string(), Json::Object(p));
+                image_layer
+            }
+        };
+        layer.insert("parallaxx".to_string(), Json::F64(rate_x as f64));
+        layer.insert("parallaxy".to_string(), Json::F64(rate_y as f64));
+        Json::Object(layer)
+    }).collect();
+
+    for tile_layer in level.tiles.layers().iter() {
+        let mut p = Object::new();
+        set(&mut p, "collision", tile_layer.collides.to_string());
+        if tile_layer.foreground {
+            set(&mut p, "foreground", "true".to_string());
+        }
+        layers.push(Json::Object(tile_layer_json(level, tile_layer, p)));
+    }
+
+    let mut objects = Vec::new();
+
+    {
+        let (x, y) = level.player_start_pos;
+        objects.push(object("player", x, y, tile_size, tile_size, Object::new()));
+    }
+
+    {
+        let writer = ObjectWriter {
+            tile_size: tile_size,
+            names: names
+        };
+        for item in level.items.iter() {
+            objects.push(item.export(&writer));
+        }
+    }
+
+    for s in level.set_tos.iter() {
+        let mut p = Object::new();
+        set_optional_trigger(&mut p, "triggered_by", s.triggered_by, names);
+        set(&mut p, "tile", s.tile.tile_type.id.to_string());
+        if let Some(layer) = s.layer {
+            set(&mut p, "layer", level.tiles.layers()[layer].name.clone());
+        }
+        objects.push(object("setto", s.x as f32 * tile_size, s.y as f32 * tile_size, s.width as f32 * tile_size, s.height as f32 * tile_size, p));
+    }
+
+    for g in level.gates.iter() {
+        let typ = match g.kind {
+            GateKind::And => "and",
+            GateKind::Or => "or",
+            GateKind::Not => "not"
+        };
+        let mut p = Object::new();
+        set_trigger_list(&mut p, "inputs", &g.inputs, names);
+        set_trigger(&mut p, "trigger", g.trigger, names);
+        objects.push(object(typ, 0.0, 0.0, tile_size, tile_size, p));
+    }
+
+    for c in level.counters.iter() {
+        let mut p = Object::new();
+        set_trigger_list(&mut p, "inputs", &c.inputs, names);
+        set(&mut p, "count", c.count.to_string());
+        set_trigger(&mut p, "trigger", c.trigger, names);
+        objects.push(object("counter", 0.0, 0.0, tile_size, tile_size, p));
+    }
+
+    for t in level.timers.iter() {
+        let mut p = Object::new();
+        set_trigger_list(&mut p, "inputs", &t.inputs, names);
+        set(&mut p, "delay", t.delay.to_string());
+        set_trigger(&mut p, "trigger", t.trigger, names);
+        objects.push(object("timer", 0.0, 0.0, tile_size, tile_size, p));
+    }
+
+    let mut object_layer = Object::new();
+    object_layer.insert("name".to_string(), Json::String(OBJECT_LAYER_NAME.to_string()));
+    object_layer.insert("type".to_string(), Json::String("objectgroup".to_string()));
+    object_layer.insert("draworder".to_string(), Json::String("topdown".to_string()));
+    object_layer.insert("width".to_string(), Json::U64(0));
+    object_layer.insert("height".to_string(), Json::U64(0));
+    object_layer.insert("x".to_string(), Json::U64(0));
+    object_layer.insert("y".to_string(), Json::U64(0));
+    object_layer.insert("opacity".to_string(), Json::U64(1));
+    object_layer.insert("visible".to_string(), Json::Boolean(true));
+    object_layer.insert("objects".to_string(), Json::Array(objects));
+    layers.push(Json::Object(object_layer));
+
+    let tilesets = if level.tile_types.tilesets.is_empty() {
+        vec![Tileset {
+            name: TILESET_NAME.to_string(),
+            first_gid: 1,
+            image: Some(GAME_TILESET_IMAGE.to_string()),
+            image_size: Some(((TILESET_COLUMNS as f32 * tile_size) as u32, (TILESET_ROWS as f32 * tile_size) as u32))
+        }]
+    } else {
+        level.tile_types.tilesets.clone()
+    };
+
+    let tilesets_json = tilesets.iter().map(|tileset| {
+        let mut tile_properties = Object::new();
+        for (_, tile_type) in level.tile_types.types.iter() {
+            if level.tile_types.tileset_of(tile_type.id).map(|t| t.first_gid) != Some(tileset.first_gid) {
+                continue;
+            }
+
+            let properties = tile_type_properties(tile_type);
+            if !properties.is_empty() {
+                // Keyed by the ID local to the tileset
+                tile_properties.insert((tile_type.id - tileset.first_gid).to_string(), Json::Object(properties));
+            }
+        }
+
+        let mut t = Object::new();
+        t.insert("firstgid".to_string(), Json::U64(tileset.first_gid as u64));
+        t.insert("name".to_string(), Json::String(tileset.name.clone()));
+        if let Some(ref image) = tileset.image {
+            t.insert("image".to_string(), Json::String(image.clone()));
+        }
+        if let Some((width, height)) = tileset.image_size {
+            t.insert("imagewidth".to_string(), Json::U64(width as u64));
+            t.insert("imageheight".to_string(), Json::U64(height as u64));
+        }
+        t.insert("tilewidth".to_string(), Json::U64(tile_size as u64));
+        t.insert("tileheight".to_string(), Json::U64(tile_size as u64));
+        t.insert("margin".to_string(), Json::U64(0));
+        t.insert("spacing".to_string(), Json::U64(0));
+        t.insert("properties".to_string(), Json::Object(Object::new()));
+        t.insert("tileproperties".to_string(), Json::Object(tile_properties));
+        Json::Object(t)
+    }).collect();
+
+    let mut map_properties = Object::new();
+    if !level.keep_inventory {
+        set(&mut map_properties, "inventory", "reset".to_string());
+    }
+    if level.checkpoint_restore != CheckpointRestore::Nothing {
+        set(&mut map_properties, "checkpoint_restore", level.checkpoint_restore.name().to_string());
+    }
+    if !names.declared().is_empty() {
+        set(&mut map_properties, "triggers", names.declared().connect(", "));
+    }
+    for &(ref name, value) in level.physics.iter() {
+        set(&mut map_properties, &format!("{}{}", PHYSICS_PROPERTY_PREFIX, name), value.to_string());
+    }
+
+    let mut map = Object::new();
+    map.insert("version".to_string(), Json::U64(1));
+    map.insert("orientation".to_string(), Json::String("orthogonal".to_string()));
+    map.insert("renderorder".to_string(), Json::String("right-down".to_string()));
+    map.insert("width".to_string(), Json::U64(level.width as u64));
+    map.insert("height".to_string(), Json::U64(level.height as u64));
+    map.insert("tilewidth".to_string(), Json::U64(tile_size as u64));
+    map.insert("tileheight".to_string(), Json::U64(tile_size as u64));
+    map.insert("properties".to_string(), Json::Object(map_properties));
+    if let Some(color) = level.background_color {
+        map.insert("backgroundcolor".to_string(), Json::String(properties::format_color(color)));
+    }
+    map.insert("tilesets".to_string(), Json::Array(tilesets_json));
+    map.insert("layers".to_string(), Json::Array(layers));
+
+    Json::Object(map)
+}
+
+fn tile_gid(tile: &Tile) -> u64 {
+    let mut gid = tile.tile_type.id as u64;
+    if tile.flip_x { gid |= 0x80000000; }
+    if tile.flip_y { gid |= 0x40000000; }
+    if tile.flip_diagonal { gid |= 0x20000000; }
+    gid
+}
+
+fn tile_layer_json(level: &Level, layer: &TileLayer, properties: Object) -> Object {
+    let data = layer.iter().map(|(_, _, tile)| Json::U64(tile_gid(tile))).collect();
+
+    let mut tile_layer = Object::new();
+    tile_layer.insert("name".to_string(), Json::String(layer.name.clone()));
+    tile_layer.insert("type".to_string(), Json::String("tilelayer".to_string()));
+    tile_layer.insert("width".to_string(), Json::U64(level.width as u64));
+    tile_layer.insert("height".to_string(), Json::U64(level.height as u64));
+    tile_layer.insert("x".to_string(), Json::U64(0));
+    tile_layer.insert("y".to_string(), Json::U64(0));
+    tile_layer.insert("opacity".to_string(), Json::U64(1));
+    tile_layer.insert("visible".to_string(), Json::Boolean(true));
+    tile_layer.insert("properties".to_string(), Json::Object(properties));
+    tile_layer.insert("data".to_string(), Json::Array(data));
+    tile_layer
+}
+
+fn object(typ: &str, x: f32, y: f32, width: f32, height: f32, properties: Object) -> Json {
+    let mut object = Object::new();
+    object.insert("name".to_string(), Json::String(String::new()));
+    object.insert("type".to_string(), Json::String(typ.to_string()));
+    object.insert("x".to_string(), Json::F64(x as f64));
+    object.insert("y".to_string(), Json::F64(y as f64));
+    object.insert("width".to_string(), Json::F64(width as f64));
+    object.insert("height".to_string(), Json::F64(height as f64));
+    object.insert("rotation".to_string(), Json::U64(0));
+    object.insert("visible".to_string(), Json::Boolean(true));
+    object.insert("properties".to_string(), Json::Object(properties));
+    Json::Object(object)
+}
+
+/// Only the properties that differ from an ordinary solid tile
+fn tile_type_properties(tile_type: &TileType) -> Object {
+    let mut properties = Object::new();
+    // Ladders aren't blocking unless they say so
+    if tile_type.is_blocking != (tile_type.kind != TileKind::Ladder) {
+        set(&mut properties, "blocking", tile_type.is_blocking.to_string());
+    }
+    if tile_type.can_dig {
+        set(&mut properties, "diggable", "true".to_string());
+    }
+    if let Some(name) = tile_type.kind.name() {
+        set(&mut properties, "kind", name.to_string());
+    }
+    if let Some(name) = tile_type.shape.name() {
+        set(&mut properties, "shape", name.to_string());
+    }
+    properties
+}
+
+fn set(properties: &mut Object, key: &str, value: String) {
+    properties.insert(key.to_string(), Json::String(value));
+}
+
+fn set_trigger(properties: &mut Object, key: &str, trigger: TriggerId, names: &TriggerNames) {
+    set(properties, key, names.name_of(trigger));
+}
+
+fn set_optional_trigger(properties: &mut Object, key: &str, trigger: Option<TriggerId>, names: &TriggerNames) {
+    if let Some(trigger) = trigger {
+        set_trigger(properties, key, trigger, names);
+    }
+}
+
+fn set_trigger_list(properties: &mut Object, key: &str, triggers: &[TriggerId], names: &TriggerNames) {
+    let list: Vec<String> = triggers.iter().map(|&t| names.name_of(t)).collect();
+    set(properties, key, list.connect(", "));
+}
+
+#[cfg(test)]
+mod test {
+    use super::to_json;
+    use super::super::{parse_from_json, ObjectTypes, UnknownObjects};
+
+    fn round_trip(input: &str) {
+        let object_types = ObjectTypes::builtin(UnknownObjects::Error);
+
+        let level = match parse_from_json(input, &object_types) {
+            Ok(level) => level,
+            Err(e) => panic!("{}", e)
+        };
+
+        let exported = format!("{}", to_json(&level));
+
+        let reparsed = match parse_from_json(&exported, &object_types) {
+            Ok(level) => level,
+            Err(e) => panic!("Exported level doesn't parse: {}", e)
+        };
+
+        assert!(level == reparsed);
+    }
+
+    #[test]
+    fn built_in_level() {
+        round_trip(include_str!("../../../assets/level.json"));
+    }
+
+    #[test]
+    fn every_object_type() {
+        round_trip(r##"{
+            "width": 2, "height": 2, "tilewidth": 16, "tileheight": 16, "backgroundcolor": "#80393a58",
+            "properties": { "inventory": "reset", "checkpoint_restore": "doors", "triggers": "door, lights", "physics.gravity": "0.25", "physics.coyote_steps": 10 },
+            "tilesets": [{
+                "firstgid": 1, "name": "tileset",
+                "tileproperties": {
+                    "0": { "blocking": "false", "kind": "coin" },
+                    "1": { "diggable": "true", "shape": "slope" }
+                }
+            }, {
+                "firstgid": 3, "name": "other", "image": "other.png", "imagewidth": 32, "imageheight": 16,
+                "tileproperties": {
+                    "0": { "blocking": "false" },
+                    "1": { "kind": "ladder" },
+                    "2": { "shape": "platform" }
+                }
+            }],
+            "layers": [
+                { "name": "stars", "type": "imagelayer", "image": "stars.png", "parallaxx": 0.5, "parallaxy": 0.25,
+                  "properties": { "tile": "1" } },
+                { "name": "hills", "parallaxx": 0.75, "data": [1, 0, 0, 1] },
+                { "name": "tiles", "data": [0, 1, 2684354562, 1610612739] },
+                { "name": "overlay", "properties": { "foreground": "true" }, "data": [0, 0, 1, 0] },
+                { "name": "objects", "objects": [
+                    { "type": "player", "x": 16, "y": 0, "width": 16, "height": 16 },
+                    { "type": "switch", "x": 0, "y": 0, "width": 16, "height": 16,
+                      "properties": { "trigger": "door", "mode": "timed", "timeout": "2.5" } },
+                    { "type": "switch", "x": 0, "y": 16, "width": 16, "height": 16,
+                      "properties": { "trigger": "3", "triggered_by": "game:exit", "mode": "toggle" } },
+                    { "type": "chest", "x": 0, "y": 0, "width": 16, "height": 40,
+                      "properties": { "contains": "gun", "trigger": "4", "explode_trigger": "lights", "poof": "true" } },
+                    { "type": "beanstalk", "x": 16, "y": 0, "width": 16, "height": 32,
+                      "properties": { "triggered_by": "door" } },
+                    { "type": "monster1", "x": 0, "y": 0, "width": 16, "height": 16 },
+                    { "type": "monster2", "x": 0, "y": 0, "width": 16, "height": 16,
+                      "properties": { "triggered_by": "lights" } },
+                    { "type": "stickykey", "x": 8, "y": 0, "width": 16, "height": 24 },
+                    { "type": "checkpoint", "x": 16, "y": 16, "width": 16, "height": 16,
+                      "properties": { "trigger": "lights" } },
+                    { "type": "message", "x": 0, "y": 0, "width": 32, "height": 16,
+                      "properties": { "tiles": "40 4a", "triggered_by": "3" } },
+                    { "type": "setto", "x": 16, "y": 16, "width": 16, "height": 16,
+                      "properties": { "tile": "2", "triggered_by": "door" } },
+                    { "type": "setto", "x": 0, "y": 0, "width": 32, "height": 16,
+                      "properties": { "tile": "1", "layer": "overlay", "triggered_by": "lights" } },
+                    { "type": "and", "x": 0, "y": 0, "width": 16, "height": 16,
+                      "properties": { "inputs": "door, 3", "trigger": "5" } },
+                    { "type": "counter", "x": 0, "y": 0, "width": 16, "height": 16,
+                      "properties": { "inputs": "lights", "count": "3", "trigger": "6" } },
+                    { "type": "timer", "x": 0, "y": 0, "width": 16, "height": 16,
+                      "properties": { "inputs": "6", "delay": "0.5", "trigger": "lights" } }
+                ] }
+            ]
+        }"##);
+    }
+}
@@ -0,0 +1,287 @@
+use rustc_serialize::json::{Json, Object};
+use super::{check_map_size, MapData, LayerData, LayerContents, ObjectData, TileData, Tileset, LevelError, Location};
+use super::properties;
+
+pub fn parse(input: &str) -> Result<MapData, LevelError> {
+    use std::str::FromStr;
+
+    let json = match FromStr::from_str(input) {
+        Ok(Json::Object(obj)) => obj,
+        Err(e) => return Err(LevelError::Syntax(format!("{}", e))),
+        _ => return Err(LevelError::Syntax("Not a JSON object".to_string())),
+    };
+
+    let map = &Location::Map;
+
+    let width = try!(get_u32(map, &json, "width"));
+    let height = try!(get_u32(map, &json, "height"));
+    let tile_width = try!(get_u32(map, &json, "tilewidth"));
+    let tile_height = try!(get_u32(map, &json, "tileheight"));
+
+    // Only square tiles are supported
+    if tile_width != tile_height {
+        return Err(LevelError::InvalidField(map.clone(), "tileheight", "must equal 'tilewidth'".to_string()));
+    }
+    try!(check_map_size(width, height, tile_width));
+    let tile_size = tile_width as f32;
+
+    let layers = try!(get_array(map, &json, "layers"));
+
+    let mut tilesets = Vec::new();
+    let mut tile_data = Vec::new();
+
+    for tileset_json in try!(get_array(map, &json, "tilesets")).iter() {
+        let tileset = match tileset_json.as_object() {
+            Some(tileset) => tileset,
+            None => return Err(LevelError::InvalidField(map.clone(), "tilesets", "must only contain JSON objects".to_string()))
+        };
+
+        let first_gid = try!(get_u32(map, tileset, "firstgid"));
+        let name = match tileset.get("name").and_then(|n| n.as_string()) {
+            Some(name) => name.to_string(),
+            None => String::new()
+        };
+
+        let image = tileset.get("image").and_then(|i| i.as_string()).map(|i| i.to_string());
+        let image_size = match (tileset.get("imagewidth").and_then(|w| w.as_u64()), tileset.get("imageheight").and_then(|h| h.as_u64())) {
+            (Some(w), Some(h)) => Some((w as u32, h as u32)),
+            _ => None
+        };
+
+        tilesets.push(Tileset {
+            name: name.clone(),
+            first_gid: first_gid,
+            image: image,
+            image_size: image_size
+        });
+
+        // Older versions of Tiled: objects keyed by the tile ID local to the tileset
+        if let Some(tile_properties) = tileset.get("tileproperties") {
+            let tile_properties = match tile_properties.as_object() {
+                Some(p) => p,
+                None => return Err(LevelError::InvalidField(map.clone(), "tileproperties", "is not a JSON object".to_string()))
+            };
+            let tile_property_types = match tileset.get("tilepropertytypes") {
+                Some(t) => match t.as_object() {
+                    Some(t) => Some(t),
+                    None => return Err(LevelError::InvalidField(map.clone(), "tilepropertytypes", "is not a JSON object".to_string()))
+                },
+                None => None
+            };
+
+            for (id_str, values) in tile_properties.iter() {
+                let id: u32 = match id_str.parse() {
+                    Ok(id) => id,
+                    Err(_) => return Err(LevelError::InvalidField(map.clone(), "tileproperties", format!("has an invalid tile ID: {}", id_str)))
+                };
+                let loc = Location::Tile { tileset: name.clone(), id: id };
+
+                let values = match values.as_object() {
+                    Some(v) => v,
+                    None => return Err(LevelError::InvalidField(loc, "tileproperties", "is not a JSON object".to_string()))
+                };
+                let types = tile_property_types.and_then(|t| t.get(id_str)).and_then(|t| t.as_object());
+
+                let mut typed_values = Object::new();
+                for (property, value) in values.iter() {
+                    let typ = types.and_then(|t| t.get(property)).and_then(|t| t.as_string());
+                    typed_values.insert(property.clone(), try!(properties::from_json(&loc, property, typ, value)));
+                }
+
+                tile_data.push(TileData {
+                    tileset: name.clone(),
+                    id: id,
+                    gid: first_gid + id,
+                    properties: typed_values
+                });
+            }
+        }
+
+        // Newer versions of Tiled: an array of tiles, each with its ID
+        if let Some(&Json::Array(ref tiles_json)) = tileset.get("tiles") {
+            for tile in tiles_json.iter() {
+                let tile = match tile.as_object() {
+                    Some(tile) => tile,
+                    None => return Err(LevelError::InvalidField(map.clone(), "tiles", "must only contain JSON objects".to_string()))
+                };
+
+                let id = try!(get_u32(map, tile, "id"));
+                let loc = Location::Tile { tileset: name.clone(), id: id };
+
+                tile_data.push(TileData {
+                    tileset: name.clone(),
+                    id: id,
+                    gid: first_gid + id,
+                    properties: try!(properties::read_json(&loc, tile, "properties", "propertytypes"))
+                });
+            }
+        }
+    }
+
+    let mut layer_data = Vec::new();
+    let mut has_tiles = false;
+    let mut objects = Vec::new();
+
+    for (index, layer) in layers.iter().enumerate() {
+        let layer = match layer.as_object() {
+            Some(layer) => layer,
+            None => return Err(LevelError::InvalidField(map.clone(), "layers", "must only contain JSON objects".to_string()))
+        };
+        let name = get_layer_name(layer, index);
+        let loc = Location::Layer(name.clone());
+
+        // Layers hidden in Tiled are only there for the level designer
+        if layer.get("visible").and_then(|v| v.as_boolean()) == Some(false) {
+            continue;
+        }
+
+        // Layers are told apart by type. Old maps might not have one.
+        let typ = match layer.get("type").and_then(|t| t.as_string()) {
+            Some(typ) => typ,
+            None if layer.contains_key("data") => "tilelayer",
+            None if layer.contains_key("objects") => "objectgroup",
+            None => ""
+        };
+
+        match typ {
+            "tilelayer" => {
+                layer_data.push(try!(parse_tile_layer(&loc, name, layer)));
+                has_tiles = true;
+            },
+            "imagelayer" => {
+                let image = layer.get("image").and_then(|i| i.as_string()).map(|i| i.to_string());
+                layer_data.push(LayerData {
+                    name: name,
+                    contents: LayerContents::Image(image),
+                    parallax: try!(get_parallax(&loc, layer)),
+                    properties: try!(properties::read_json(&loc, layer, "properties", "propertytypes"))
+                });
+            },
+            "objectgroup" => {
+                for (index, x) in try!(get_array(&loc, layer, "objects")).iter().enumerate() {
+                    objects.push(try!(parse_object(&name, index, x)));
+                }
+            },
+            // Groups and the like aren't used
+            _ => ()
+        }
+    }
+
+    if !has_tiles {
+        return Err(LevelError::InvalidField(map.clone(), "layers", "must have a tile layer".to_string()));
+    }
+
+    let map_properties = try!(properties::read_json(map, &json, "properties", "propertytypes"));
+    let background_color = json.get("backgroundcolor").and_then(|c| c.as_string()).map(|c| c.to_string());
+
+    Ok(MapData {
+        width: width,
+        height: height,
+        tile_size: tile_size,
+        background_color: background_color,
+        layers: layer_data,
+        tilesets: tilesets,
+        tile_data: tile_data,
+        objects: objects,
+        properties: map_properties
+    })
+}
+
+fn parse_tile_layer(location: &Location, name: String, layer: &Object) -> Result<LayerData, LevelError> {
+    let mut tiles: Vec<u32> = Vec::new();
+    for num_json in try!(get_array(location, layer, "data")).iter() {
+        match num_json.as_u64() {
+            Some(value) => tiles.push(value as u32),
+            None => return Err(LevelError::InvalidField(location.clone(), "data", "must only contain numbers".to_string()))
+        }
+    }
+
+    Ok(LayerData {
+        name: name,
+        contents: LayerContents::Tiles(tiles),
+        parallax: try!(get_parallax(location, layer)),
+        properties: try!(properties::read_json(location, layer, "properties", "propertytypes"))
+    })
+}
+
+fn parse_object(layer: &str, index: usize, x: &Json) -> Result<ObjectData, LevelError> {
+    let untyped_loc = Location::Object {
+        layer: layer.to_string(),
+        index: index,
+        typ: String::new()
+    };
+
+    let object = match x.as_object() {
+        Some(object) => object,
+        None => return Err(LevelError::InvalidField(untyped_loc, "objects", "must only contain JSON objects".to_string()))
+    };
+
+    let typ = match object.get("type").and_then(|t| t.as_string()) {
+        Some(typ) => typ.to_string(),
+        None => return Err(LevelError::MissingField(untyped_loc, "type"))
+    };
+
+    let loc = Location::Object {
+        layer: layer.to_string(),
+        index: index,
+        typ: typ.clone()
+    };
+
+    let object_properties = try!(properties::read_json(&loc, object, "properties", "propertytypes"));
+
+    Ok(ObjectData {
+        layer: layer.to_string(),
+        index: index,
+        typ: typ,
+        x: try!(get_f32(&loc, object, "x")),
+        y: try!(get_f32(&loc, object, "y")),
+        width: try!(get_f32(&loc, object, "width")),
+        height: try!(get_f32(&loc, object, "height")),
+        properties: object_properties
+    })
+}
+
+fn get_layer_name(layer: &Object, index: usize) -> String {
+    match layer.get("name").and_then(|n| n.as_string()) {
+        Some(name) => name.to_string(),
+        None => format!("#{}", index)
+    }
+}
+
+/// Tiled's `parallaxx` and `parallaxy`, which default to 1
+fn get_parallax(location: &Location, layer: &Object) -> Result<(f32, f32), LevelError> {
+    let x = if layer.contains_key("parallaxx") { try!(get_f32(location, layer, "parallaxx")) } else { 1.0 };
+    let y = if layer.contains_key("parallaxy") { try!(get_f32(location, layer, "parallaxy")) } else { 1.0 };
+    Ok((x, y))
+}
+
+fn get_u32(location: &Location, object: &Object, key: &'static str) -> Result<u32, LevelError> {
+    match object.get(key) {
+        Some(j) => match j.as_u64() {
+            Some(value) if value <= ::std::u32::MAX as u64 => Ok(value as u32),
+            Some(_) => Err(LevelError::InvalidField(location.clone(), key, "is too large".to_string())),
+            None => Err(LevelError::InvalidField(location.clone(), key, "is not a whole number".to_string()))
+        },
+        None => Err(LevelError::MissingField(location.clone(), key))
+    }
+}
+
+fn get_f32(location: &Location, object: &Object, key: &'static str) -> Result<f32, LevelError> {
+    match object.get(key) {
+        Some(j) => match j.as_f64() {
+            Some(value) => Ok(value as f32),
+            None => Err(LevelError::InvalidField(location.clone(), key, "is not a number".to_string()))
+        },
+        None => Err(LevelError::MissingField(location.clone(), key))
+    }
+}
+
+fn get_array<'a>(location: &Location, object: &'a Object, key: &'static str) -> Result<&'a Vec<Json>, LevelError> {
+    match object.get(key) {
+        Some(j) => match j.as_array() {
+            Some(array) => Ok(array),
+            None => Err(LevelError::InvalidField(location.clone(), key, "is not a JSON array".to_string()))
+        },
+        None => Err(LevelError::MissingField(location.clone(), key))
+    }
+}
@@ -0,0 +1,1657 @@
+use rustc_serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use super::rect::Rect;
+use super::items::PlacedItems;
+use super::physics::{PlayerPhysics, SettingError};
+use super::wrapping::Screen;
+
+pub use self::error::{LevelError, Location};
+pub use self::triggers::{Signal, TriggerId, TRIGGER_EXIT};
+
+pub use self::export::ObjectWriter;
+pub use self::objects::{ObjectContext, ObjectParser, ObjectTypes, LevelObjects, UnknownObjects};
+
+use self::triggers::TriggerNames;
+
+mod error;
+mod export;
+mod json;
+mod objects;
+mod properties;
+mod tmx;
+mod triggers;
+
+/// The file name of the game's tileset image. Tiles from other images can't be drawn.
+pub static GAME_TILESET_IMAGE: &'static str = "tileset.png";
+
+/// Map properties starting with this change the player's physics, e.g. `physics.gravity`
+pub static PHYSICS_PROPERTY_PREFIX: &'static str = "physics.";
+
+#[derive(Clone, PartialEq)]
+pub struct Tile {
+    pub tile_type: TileType,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    /// Swaps the x and y axes. Tiled applies this before the other two flips.
+    pub flip_diagonal: bool
+}
+
+impl Tile {
+    pub fn empty() -> Tile {
+        Tile {
+            tile_type: TileType::empty(),
+            flip_x: false,
+            flip_y: false,
+            flip_diagonal: false
+        }
+    }
+
+    /// Reads a Tiled global tile ID, including its flip bits
+    fn from_gid(location: &Location, tile_types: &TileTypes, value: u32) -> Result<Tile, LevelError> {
+        let id = value & 0x1FFFFFFF;
+
+        let tile_type = match tile_types.get(id) {
+            Some(tile_type) => tile_type,
+            None => return Err(LevelError::UnknownTile(location.clone(), id))
+        };
+
+        Ok(Tile {
+            tile_type: tile_type,
+            flip_x: (value & 0x80000000) != 0,
+            flip_y: (value & 0x40000000) != 0,
+            flip_diagonal: (value & 0x20000000) != 0
+        })
+    }
+
+    /// How high the surface is at the tile's left and right edges, if it's a blocking slope.
+    /// Only horizontal flips are taken into account.
+    fn slope_heights(&self) -> Option<(f32, f32)> {
+        if !self.tile_type.is_blocking {
+            return None;
+        }
+        match self.tile_type.shape.slope_heights() {
+            Some((left, right)) if self.flip_x => Some((right, left)),
+            heights => heights
+        }
+    }
+
+    /// The flips and rotation to draw the tile with: ((flip_x, flip_y), rotate_90)
+    pub fn draw_transform(&self) -> ((bool, bool), bool) {
+        if self.flip_diagonal {
+            // A diagonal flip is the same as rotating 90 degrees clockwise and flipping horizontally
+            ((!self.flip_x, self.flip_y), true)
+        } else {
+            ((self.flip_x, self.flip_y), false)
+        }
+    }
+}
+
+/// A tileset as declared in the map
+#[derive(Clone, PartialEq)]
+pub struct Tileset {
+    pub name: String,
+    pub first_gid: u32,
+    /// As written in the map, relative to it
+    pub image: Option<String>,
+    /// In pixels
+    pub image_size: Option<(u32, u32)>
+}
+
+impl Tileset {
+    /// Whether the tiles come from the game's own tileset image.
+    /// Tilesets without an image are assumed to.
+    pub fn is_drawable(&self) -> bool {
+        match self.image {
+            Some(ref image) => Path::new(image).file_name().and_then(|f| f.to_str()) == Some(GAME_TILESET_IMAGE),
+            None => true
+        }
+    }
+}
+
+/// What a tile does when the player interacts with it
+#[derive(Copy, Clone, PartialEq)]
+pub enum TileKind {
+    Normal,
+    Coin,
+    Exit,
+    /// Opened with a key
+    Door,
+    /// Where the player can start digging, and where they emerge from
+    DirtEntrance,
+    /// Climbed like a beanstalk
+    Ladder
+}
+
+impl TileKind {
+    /// The name used by the `kind` tile property in level files
+    pub fn from_name(name: &str) -> Option<TileKind> {
+        match name {
+            "coin" => Some(TileKind::Coin),
+            "exit" => Some(TileKind::Exit),
+            "door" => Some(TileKind::Door),
+            "dirt_entrance" => Some(TileKind::DirtEntrance),
+            "ladder" => Some(TileKind::Ladder),
+            _ => None
+        }
+    }
+
+    /// None for normal tiles, which don't need a `kind` property
+    pub fn name(&self) -> Option<&'static str> {
+        match *self {
+            TileKind::Normal => None,
+            TileKind::Coin => Some("coin"),
+            TileKind::Exit => Some("exit"),
+            TileKind::Door => Some("door"),
+            TileKind::DirtEntrance => Some("dirt_entrance"),
+            TileKind::Ladder => Some("ladder")
+        }
+    }
+}
+
+/// Which part of a blocking tile is solid
+#[derive(Copy, Clone, PartialEq)]
+pub enum TileShape {
+    Full,
+    /// Only the top, and only for the player falling onto it
+    Platform,
+    /// A 45 degree ramp rising to the right. Flip the tile for one rising to the left.
+    Slope,
+    /// The lower half of a 22.5 degree ramp rising to the right, which takes two tiles
+    GentleSlopeLow,
+    /// The upper half of a 22.5 degree ramp
+    GentleSlopeHigh
+}
+
+impl TileShape {
+    /// The name used by the `shape` tile property in level files
+    pub fn from_name(name: &str) -> Option<TileShape> {
+        match name {
+            "full" => Some(TileShape::Full),
+            "platform" => Some(TileShape::Platform),
+            "slope" => Some(TileShape::Slope),
+            "gentle_slope_low" => Some(TileShape::GentleSlopeLow),
+            "gentle_slope_high" => Some(TileShape::GentleSlopeHigh),
+            _ => None
+        }
+    }
+
+    /// None for full tiles, which don't need a `shape` property
+    pub fn name(&self) -> Option<&'static str> {
+        match *self {
+            TileShape::Full => None,
+            TileShape::Platform => Some("platform"),
+            TileShape::Slope => Some("slope"),
+            TileShape::GentleSlopeLow => Some("gentle_slope_low"),
+            TileShape::GentleSlopeHigh => Some("gentle_slope_high")
+        }
+    }
+
+    /// How high a slope's surface is at its left and right edges, as fractions of the tile size
+    fn slope_heights(&self) -> Option<(f32, f32)> {
+        match *self {
+            TileShape::Slope => Some((0.0, 1.0)),
+            TileShape::GentleSlopeLow => Some((0.0, 0.5)),
+            TileShape::GentleSlopeHigh => Some((0.5, 1.0)),
+            _ => None
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct TileType {
+    /// The global tile ID
+    pub id: u32,
+    /// Where the tile is in the game's tileset image, if it's in there at all
+    pub atlas_id: Option<u16>,
+    pub is_blocking: bool,
+    pub can_dig: bool,
+    pub kind: TileKind,
+    /// Only matters for blocking tiles
+    pub shape: TileShape
+}
+
+impl TileType {
+    pub fn empty() -> TileType {
+        TileType {
+            id: 0,
+            atlas_id: None,
+            is_blocking: false,
+            can_dig: false,
+            kind: TileKind::Normal,
+            shape: TileShape::Full
+        }
+    }
+
+    /// A tile that has no properties in the tileset
+    fn solid(id: u32, atlas_id: Option<u16>) -> TileType {
+        TileType {
+            id: id,
+            atlas_id: atlas_id,
+            is_blocking: true,
+            can_dig: false,
+            kind: TileKind::Normal,
+            shape: TileShape::Full
+        }
+    }
+}
+
+/// The tile types defined by a level's tilesets
+#[derive(PartialEq)]
+pub struct TileTypes {
+    tilesets: Vec<Tileset>,
+    types: HashMap<u32, TileType>
+}
+
+impl TileTypes {
+    /// The type of a global tile ID. Returns None if none of the tilesets cover it.
+    pub fn get(&self, id: u32) -> Option<TileType> {
+        if id == 0 {
+            return Some(TileType::empty());
+        }
+
+        match (self.types.get(&id), self.tileset_of(id)) {
+            (Some(tile_type), _) => Some(tile_type.clone()),
+            (None, Some(_)) => Some(TileType::solid(id, self.atlas_id(id))),
+            (None, None) => None
+        }
+    }
+
+    /// The tileset a global tile ID belongs to: the one with the highest `first_gid` that's not above it
+    pub fn tileset_of(&self, gid: u32) -> Option<&Tileset> {
+        let mut found: Option<&Tileset> = None;
+        for tileset in self.tilesets.iter().filter(|t| t.first_gid <= gid) {
+            match found {
+                Some(f) if f.first_gid >= tileset.first_gid => (),
+                _ => found = Some(tileset)
+            }
+        }
+        found
+    }
+
+    fn atlas_id(&self, gid: u32) -> Option<u16> {
+        match self.tileset_of(gid) {
+            // The game's tileset image is nowhere near big enough for IDs past a u16
+            Some(tileset) if tileset.is_drawable() && gid - tileset.first_gid <= ::std::u16::MAX as u32 => Some((gid - tileset.first_gid) as u16),
+            _ => None
+        }
+    }
+}
+
+/// How much of the level dying puts back the way it was at the last checkpoint
+#[derive(Copy, Clone, PartialEq)]
+pub enum CheckpointRestore {
+    /// Nothing. The player just comes back at the checkpoint.
+    Nothing,
+    /// Doors the player opened since, and the keys they used
+    Doors,
+    /// The tiles, items, triggers and the player's inventory
+    Everything
+}
+
+impl CheckpointRestore {
+    /// The name used by the `checkpoint_restore` map property
+    pub fn from_name(name: &str) -> Option<CheckpointRestore> {
+        match name {
+            "nothing" => Some(CheckpointRestore::Nothing),
+            "doors" => Some(CheckpointRestore::Doors),
+            "everything" => Some(CheckpointRestore::Everything),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            CheckpointRestore::Nothing => "nothing",
+            CheckpointRestore::Doors => "doors",
+            CheckpointRestore::Everything => "everything"
+        }
+    }
+}
+
+#[derive(PartialEq)]
+pub struct SetTo {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub tile: Tile,
+    /// The tile layer to change. If None, the first layer that collides.
+    pub layer: Option<usize>,
+    pub triggered_by: Option<TriggerId>,
+    /// The tiles that were there before the SetTo was applied, so it can be reverted
+    replaced: Option<Vec<Tile>>
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum GateKind {
+    And,
+    Or,
+    Not
+}
+
+/// Fires `trigger` when its inputs start satisfying the gate, and releases it when they stop
+#[derive(PartialEq)]
+pub struct Gate {
+    pub kind: GateKind,
+    pub inputs: Vec<TriggerId>,
+    pub trigger: TriggerId
+}
+
+/// Fires `trigger` after its inputs have fired `count` times
+#[derive(PartialEq)]
+pub struct Counter {
+    pub inputs: Vec<TriggerId>,
+    pub count: u32,
+    pub trigger: TriggerId
+}
+
+/// Fires `trigger` a while after any of its inputs fire
+#[derive(PartialEq)]
+pub struct Timer {
+    pub inputs: Vec<TriggerId>,
+    /// In seconds
+    pub delay: f32,
+    pub trigger: TriggerId
+}
+
+/// One of the map's tile layers
+#[derive(Clone, PartialEq)]
+pub struct TileLayer {
+    pub name: String,
+    /// Whether the layer's tiles block the player, can be dug, and so on
+    pub collides: bool,
+    /// Drawn over the player and items instead of under them
+    pub foreground: bool,
+    width: u32,
+    tiles: Vec<Tile>
+}
+
+impl TileLayer {
+    pub fn new(name: String, width: u32, tiles: Vec<Tile>) -> TileLayer {
+        TileLayer {
+            name: name,
+            collides: true,
+            foreground: false,
+            width: width,
+            tiles: tiles
+        }
+    }
+
+    pub fn iter(&self) -> LevelTileIterator {
+        LevelTileIterator {
+            tiles: &self.tiles,
+            width: self.width as usize,
+            index: 0
+        }
+    }
+}
+
+/// What a parallax layer draws
+#[derive(PartialEq)]
+pub enum ParallaxSource {
+    /// One tile repeated across the whole level
+    Tile(Tile),
+    Layer(TileLayer)
+}
+
+/// A layer drawn behind the tiles that scrolls at its own rate
+#[derive(PartialEq)]
+pub struct ParallaxLayer {
+    pub name: String,
+    pub source: ParallaxSource,
+    /// How far the layer scrolls for each pixel the view scrolls, per axis
+    pub rate: (f32, f32)
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Tiles {
+    width: u32,
+    height: u32,
+    screen: Screen,
+    /// In draw order, bottom first
+    layers: Vec<TileLayer>,
+    tile_size: f32,
+    /// What `get_tile` gives where no colliding layer has a tile
+    empty: Tile
+}
+
+impl Tiles {
+    pub fn new(width: u32, height: u32, layers: Vec<TileLayer>, tile_size: f32) -> Tiles {
+        Tiles {
+            width: width,
+            height: height,
+            screen: Screen::new(width as f32 * tile_size, height as f32 * tile_size, tile_size),
+            layers: layers,
+            tile_size: tile_size,
+            empty: Tile::empty()
+        }
+    }
+
+    pub fn tile_size(&self) -> f32 { self.tile_size }
+
+    pub fn layers(&self) -> &[TileLayer] { &self.layers }
+
+    /// The first layer that collides, which SetTos change unless told otherwise
+    fn main_layer(&self) -> Option<usize> {
+        self.layers.iter().position(|l| l.collides)
+    }
+
+    fn offset(&self, x: u32, y: u32) -> usize {
+        y as usize * self.width as usize + x as usize
+    }
+
+    /// The topmost colliding layer that has a tile at x, y
+    fn collision_layer_at(&self, x: u32, y: u32) -> Option<usize> {
+        let offset = self.offset(x, y);
+        self.layers.iter().enumerate().rev()
+            .filter(|&(_, l)| l.collides)
+            .find(|&(_, l)| l.tiles[offset].tile_type.id != 0)
+            .map(|(i, _)| i)
+    }
+
+    /// The tile that counts for collision: the topmost one of the colliding layers
+    pub fn get_tile(&self, x: u32, y: u32) -> &Tile {
+        match self.collision_layer_at(x, y) {
+            Some(layer) => &self.layers[layer].tiles[self.offset(x, y)],
+            None => &self.empty
+        }
+    }
+
+    /// Replaces the tile returned by `get_tile`
+    pub fn set_tile(&mut self, x: u32, y: u32, tile: Tile) {
+        if let Some(layer) = self.collision_layer_at(x, y).or(self.main_layer()) {
+            self.set_layer_tile(layer, x, y, tile);
+        }
+    }
+
+    fn set_layer_tile(&mut self, layer: usize, x: u32, y: u32, tile: Tile) {
+        let offset = self.offset(x, y);
+        self.layers[layer].tiles[offset] = tile;
+    }
+
+    /// Returns the tiles that were replaced, row by row
+    pub fn apply_set_to(&mut self, set_to: &SetTo) -> Vec<Tile> {
+        let mut replaced = Vec::new();
+        if let Some(layer) = set_to.layer.or(self.main_layer()) {
+            for y in (set_to.y..set_to.y + set_to.height) {
+                for x in (set_to.x..set_to.x + set_to.width) {
+                    let offset = self.offset(x, y);
+                    replaced.push(self.layers[layer].tiles[offset].clone());
+                    self.set_layer_tile(layer, x, y, set_to.tile.clone());
+                }
+            }
+        }
+        replaced
+    }
+
+    /// Puts back the tiles returned by `apply_set_to`
+    pub fn revert_set_to(&mut self, set_to: &SetTo, replaced: Vec<Tile>) {
+        let mut replaced = replaced.into_iter();
+        if let Some(layer) = set_to.layer.or(self.main_layer()) {
+            for y in (set_to.y..set_to.y + set_to.height) {
+                for x in (set_to.x..set_to.x + set_to.width) {
+                    if let Some(tile) = replaced.next() {
+                        self.set_layer_tile(layer, x, y, tile);
+                    }
+                }
+            }
+        }
+    }
+
+    fn nudge(tile_size: f32, x: f32, y: f32, left_top: (i32, i32), right_bottom: (i32, i32), direction: (Option<bool>, Option<bool>)) -> (f32, f32) {
+        let (left, top) = left_top;
+        let (right, bottom) = right_bottom;
+        let (nudge_right, nudge_bottom) = direction;
+
+        let new_left = match nudge_right {
+            None => x,
+            Some(false) => right as f32 * tile_size,
+            Some(true) => left as f32 * tile_size
+        };
+        let new_top = match nudge_bottom {
+            None => y,
+            Some(false) => bottom as f32 * tile_size,
+            Some(true) => top as f32 * tile_size
+        };
+        (new_left, new_top)
+    }
+
+    /// Direction: (right?, down?)
+    /// Returns: new top_left
+    pub fn collision_tile(&self, rect: &Rect<f32>, direction: (Option<bool>, Option<bool>)) -> Option<(f32, f32)> {
+        let (x, y) = rect.left_top().xy();
+
+        let (tiles, left_top, right_bottom) = self.get_tiles_in_rect(rect);
+
+        // Slopes count as whole tiles here. Only the player walks on them.
+        let nudge = tiles.iter().any(|&(t, _, _)| t.tile_type.is_blocking && t.tile_type.shape != TileShape::Platform);
+
+        if nudge {
+            Some(Tiles::nudge(self.tile_size, x, y, left_top, right_bottom, direction))
+        } else {
+            None
+        }
+    }
+
+    /// Like `collision_tile`, for the player after moving by `delta` to `rect`, one axis at a time.
+    /// Platforms and the tops of ladders only stop them falling through from above,
+    /// and slopes lift them up onto the surface instead of getting in the way.
+    /// Returns: new top_left
+    pub fn collision_walking(&self, rect: &Rect<f32>, delta: (f32, f32)) -> Option<(f32, f32)> {
+        use std::num::Float;
+
+        let (x, y) = rect.left_top().xy();
+        let (w, h) = rect.size();
+        let (dx, dy) = delta;
+
+        let direction = (
+            if dx > 0.0 { Some(true) } else if dx < 0.0 { Some(false) } else { None },
+            if dy > 0.0 { Some(true) } else if dy < 0.0 { Some(false) } else { None }
+        );
+
+        // Slopes first, so the tiles beside the top of a slope don't get in the way.
+        // Only small steps up are allowed, so slopes can't pull anything up through walls.
+        let floors = [self.floor_at(x, y + h - 1.0), self.floor_at(x + w - 1.0, y + h - 1.0)];
+        let on_slope = floors.iter().any(|f| match *f { Some((_, is_slope)) => is_slope, None => false });
+        let floor = floors.iter().fold(y + h, |lowest, f| match *f {
+            Some((floor, _)) => Float::min(lowest, floor),
+            None => lowest
+        });
+        let lifted = dy >= 0.0 && on_slope && floor < y + h && y + h - floor <= Float::abs(dx) + dy + 1.0;
+        let y = if lifted { floor - h } else { y };
+
+        let rect = &Rect::from_xywh(&self.screen, x, y, w, h);
+        let (left_top, right_bottom) = self.get_left_top_tile_coord(rect);
+
+        // Whether the rect's bottom edge was above the row it's in now
+        let row_top = Float::floor((y + h - 1.0) / self.tile_size) * self.tile_size;
+        let landed = dy > 0.0 && y + h - dy <= row_top;
+
+        let corners = [(x, y, false), (x + w - 1.0, y, false), (x, y + h - 1.0, true), (x + w - 1.0, y + h - 1.0, true)];
+        let nudge = corners.iter().any(|&(corner_x, corner_y, is_bottom)| {
+            let tile_type = &self.tile_at(corner_x, corner_y).tile_type;
+            if tile_type.is_blocking && tile_type.shape == TileShape::Platform {
+                return is_bottom && landed;
+            }
+            // The top of a ladder can be stood on
+            if !tile_type.is_blocking && tile_type.kind == TileKind::Ladder {
+                let is_top = self.tile_at(corner_x, corner_y - self.tile_size).tile_type.kind != TileKind::Ladder;
+                return is_top && is_bottom && landed;
+            }
+            // Inside the solid part of a tile
+            match self.floor_at(corner_x, corner_y) {
+                Some((floor, _)) => corner_y >= floor,
+                None => false
+            }
+        });
+
+        if nudge {
+            Some(Tiles::nudge(self.tile_size, x, y, left_top, right_bottom, direction))
+        } else if lifted {
+            Some((x, y))
+        } else {
+            None
+        }
+    }
+
+    /// The tile at a point in pixels, which wraps around
+    fn tile_at(&self, x: f32, y: f32) -> &Tile {
+        use std::num::Float;
+
+        let column = Float::floor(x / self.tile_size) as i32;
+        let row = Float::floor(y / self.tile_size) as i32;
+        let (width, height) = (self.width as i32, self.height as i32);
+
+        self.get_tile((((column % width) + width) % width) as u32, (((row % height) + height) % height) as u32)
+    }
+
+    /// Where the floor is at a point inside a blocking tile: the surface of a slope, or the top of anything else.
+    /// Platforms don't count.
+    /// Returns: (y of the floor, whether it's a slope)
+    fn floor_at(&self, x: f32, y: f32) -> Option<(f32, bool)> {
+        use std::num::Float;
+
+        let tile = self.tile_at(x, y);
+        if !tile.tile_type.is_blocking || tile.tile_type.shape == TileShape::Platform {
+            return None;
+        }
+
+        let row_top = Float::floor(y / self.tile_size) * self.tile_size;
+        match tile.slope_heights() {
+            Some((left, right)) => {
+                // Measured from the middle of the pixel
+                let column_left = Float::floor(x / self.tile_size) * self.tile_size;
+                let along = (x + 0.5 - column_left) / self.tile_size;
+                let height = left + (right - left) * along;
+                Some((row_top + self.tile_size * (1.0 - height), true))
+            },
+            None => Some((row_top, false))
+        }
+    }
+
+    /// The whole ladder the middle of `rect` is on, as one rect reaching from its bottom tile
+    /// to the tile above its top one, so there's somewhere to climb out to
+    pub fn ladder_at(&self, rect: &Rect<f32>) -> Option<Rect<f32>> {
+        use std::cmp::max;
+        use std::num::Float;
+
+        let (x, y) = rect.left_top().xy();
+        let (w, h) = rect.size();
+
+        // The rect can reach past the edges of the level, so wrap the tile coordinates like tile_at does
+        let (width, height) = (self.width as i32, self.height as i32);
+        let column = Float::floor((x + w / 2.0) / self.tile_size) as i32;
+        let column = (((column % width) + width) % width) as u32;
+        let is_ladder = |row: u32| self.get_tile(column, row % self.height).tile_type.kind == TileKind::Ladder;
+
+        let top = Float::floor(y / self.tile_size) as i32;
+        let bottom = Float::floor((y + h - 1.0) / self.tile_size) as i32;
+        let top_row = (((top % height) + height) % height) as u32;
+        let bottom_row = top_row + max(bottom - top, 0) as u32;
+        let row = match (top_row..bottom_row + 1).find(|&row| is_ladder(row)) {
+            Some(row) => row + self.height,
+            None => return None
+        };
+
+        // Rows are offset by the height, so the ladder can go up past the top of the level and wrap around
+        let mut top = row;
+        while top > row + 1 - self.height && is_ladder(top - 1) {
+            top -= 1;
+        }
+        let mut bottom = row;
+        while bottom + 1 < top + self.height && is_ladder(bottom + 1) {
+            bottom += 1;
+        }
+
+        let tile_x = column as f32 * self.tile_size;
+        let tile_y = ((top - 1) % self.height) as f32 * self.tile_size;
+        Some(Rect::from_xywh(&self.screen, tile_x, tile_y, self.tile_size, (bottom - top + 2) as f32 * self.tile_size))
+    }
+
+    /// How far `rect` gets while moving by `delta` before it first hits a blocking tile, as a fraction of `delta`.
+    /// The path is checked in steps no bigger than a tile or the rect, so nothing along it is skipped.
+    pub fn sweep(&self, rect: &Rect<f32>, delta: (f32, f32)) -> Option<f32> {
+        use std::num::Float;
+
+        let (dx, dy) = delta;
+        let (w, h) = rect.size();
+        let step_x = Float::max(Float::min(self.tile_size, w), 1.0);
+        let step_y = Float::max(Float::min(self.tile_size, h), 1.0);
+        let steps = Float::max(Float::ceil(Float::abs(dx) / step_x), Float::ceil(Float::abs(dy) / step_y)) as u32;
+
+        for i in (0..steps + 1) {
+            let t = if steps == 0 { 0.0 } else { i as f32 / steps as f32 };
+            if let Some(_) = self.collision_tile(&rect.offset(&self.screen, dx * t, dy * t), (None, None)) {
+                return Some(t);
+            }
+        }
+
+        None
+    }
+
+    pub fn collision_tile_digging(&self, rect: &Rect<f32>, direction: (Option<bool>, Option<bool>), get_emerge: bool) -> Option<(f32, f32, bool)> {
+        let (x, y) = rect.left_top().xy();
+
+        let (tiles, left_top, right_bottom) = self.get_tiles_in_rect(rect);
+
+        let nudge = tiles.iter().any(|&(t, _, _)| !t.tile_type.can_dig);
+        let emerge_hit: Vec<(i32, i32)> = tiles.iter().filter_map(|&(t, x, y)| {
+            if t.tile_type.kind == TileKind::DirtEntrance { Some((x, y)) }
+            else { None }
+        }).collect();
+
+        if get_emerge && emerge_hit.len() >= 1 {
+            let (x, y) = emerge_hit[0];
+            Some((x as f32 * self.tile_size, y as f32 * self.tile_size, true))
+        } else {
+            if nudge {
+                let (x, y) = Tiles::nudge(self.tile_size, x, y, left_top, right_bottom, direction);
+                Some((x, y, false))
+            } else {
+                None
+            }
+        }
+    }
+
+    fn get_left_top_tile_coord(&self, rect: &Rect<f32>) -> ((i32, i32), (i32, i32)) {
+        use std::num::Float;
+
+        let (x, y) = rect.left_top().xy();
+        let (w, h) = rect.size();
+
+        let (left, top) = {
+            (Float::floor(x / self.tile_size) as i32, Float::floor(y / self.tile_size) as i32)
+        };
+        let (right, bottom) = {
+            let (mut r, mut b) = (Float::floor((x + w-1.0) / self.tile_size) as i32, Float::floor((y + h-1.0) / self.tile_size) as i32);
+
+            // Wrapping
+            if r >= self.width as i32 { r -= self.width as i32 }
+            if b >= self.height as i32 { b -= self.height as i32 }
+
+            (r, b)
+        };
+
+        ((left, top), (right, bottom))
+    }
+
+    fn get_tiles_in_rect(&self, rect: &Rect<f32>) -> ([(&Tile, i32, i32); 4], (i32, i32), (i32, i32)) {
+        let ((left, top), (right, bottom)) = self.get_left_top_tile_coord(rect);
+
+        ([
+            (self.get_tile(left as u32, top as u32), left, top),
+            (self.get_tile(right as u32, top as u32), right, top),
+            (self.get_tile(left as u32, bottom as u32), left, bottom),
+            (self.get_tile(right as u32, bottom as u32), right, bottom),
+        ], (left, top), (right, bottom))
+    }
+
+    pub fn is_kind_inside(&self, rect: &Rect<f32>, kind: TileKind) -> Option<(u32, u32)> {
+        let (tiles, _left_top, _right_bottom) = self.get_tiles_in_rect(rect);
+        for &(tile, x, y) in tiles.iter() {
+            if (*tile).tile_type.kind == kind {
+                return Some((x as u32, y as u32))
+            }
+        }
+        None
+    }
+
+    pub fn is_dirt_entrance_below(&self, rect: &Rect<f32>) -> Option<(u32, u32)> {
+        self.is_kind_inside(&rect.offset(&self.screen, 0.0, self.tile_size / 4.0), TileKind::DirtEntrance)
+    }
+
+    pub fn is_key_entrance_beside(&self, rect: &Rect<f32>) -> Option<(u32, u32)> {
+        if let Some((x, y)) = self.is_kind_inside(&rect.offset(&self.screen, -self.tile_size / 4.0, 0.0), TileKind::Door) {
+            Some((x, y))
+        } else {
+            None
+        }
+    }
+
+    pub fn remove_key_entrance(&mut self, x: u32, y: u32) {
+        self.set_tile(x, y, Tile::empty());
+    }
+
+    /// Every door that's still locked, with where it is
+    pub fn doors(&self) -> Vec<(u32, u32, Tile)> {
+        let mut doors = Vec::new();
+        for y in (0..self.height) {
+            for x in (0..self.width) {
+                let tile = self.get_tile(x, y);
+                if tile.tile_type.kind == TileKind::Door {
+                    doors.push((x, y, tile.clone()));
+                }
+            }
+        }
+        doors
+    }
+
+    /// Puts back the doors from `doors` that have been opened since.
+    /// Returns true if any were.
+    pub fn relock_doors(&mut self, doors: &[(u32, u32, Tile)]) -> bool {
+        let mut relocked = false;
+        for &(x, y, ref door) in doors.iter() {
+            // Only where the door left a gap, so nothing else that's been put there is lost
+            if self.get_tile(x, y).tile_type.id == 0 {
+                self.set_tile(x, y, door.clone());
+                relocked = true;
+            }
+        }
+        relocked
+    }
+
+    pub fn has_non_blocking_tile(&self, rect: &Rect<f32>) -> Option<(u32, u32)> {
+        let (tiles, _left_top, _right_bottom) = self.get_tiles_in_rect(rect);
+        for &(tile, x, y) in tiles.iter() {
+            if !(*tile).tile_type.is_blocking {
+                return Some((x as u32, y as u32))
+            }
+        }
+        None
+    }
+
+    pub fn take_coins(&mut self, rect: &Rect<f32>) -> u32 {
+        let mut count = 0;
+        while let Some((x, y)) = self.is_kind_inside(rect, TileKind::Coin) {
+            self.set_tile(x, y, Tile::empty());
+
+            count += 1;
+        }
+
+        count
+    }
+}
+
+/// The parts of a level that change while it's played, as of some moment
+#[derive(Clone)]
+pub struct LevelState {
+    tiles: Tiles,
+    /// What each of the level's SetTos has replaced, in the same order
+    set_to_replaced: Vec<Option<Vec<Tile>>>
+}
+
+#[derive(PartialEq)]
+pub struct Level {
+    pub width: u32,
+    pub height: u32,
+    pub tile_size: f32,
+    pub player_start_pos: (f32, f32),
+    /// If false, the player starts this level without the items from previous levels
+    pub keep_inventory: bool,
+    /// What the screen is cleared to, as (red, green, blue, alpha)
+    pub background_color: Option<(u8, u8, u8, u8)>,
+    /// Player physics settings this level changes, by name
+    pub physics: Vec<(String, f32)>,
+    pub checkpoint_restore: CheckpointRestore,
+    /// Bottom first. Levels without any get the game's own backdrop.
+    pub parallax_layers: Vec<ParallaxLayer>,
+    tiles: Tiles,
+    tile_types: TileTypes,
+    trigger_names: TriggerNames,
+    pub items: PlacedItems,
+    pub set_tos: Vec<SetTo>,
+    pub gates: Vec<Gate>,
+    pub counters: Vec<Counter>,
+    pub timers: Vec<Timer>
+}
+
+impl Level {
+    /// Loads the level that's embedded in the executable
+    pub fn load() -> Level {
+        let level_data = include_str!("../../../assets/level.json");
+
+        match parse_from_json(level_data, &ObjectTypes::builtin(UnknownObjects::Error)) {
+            Ok(level) => level,
+            Err(e) => panic!("Built-in level is broken: {}", e)
+        }
+    }
+
+    /// Loads a Tiled map from disk.
+    /// Files ending in `.tmx` are read as TMX, and everything else as JSON.
+    pub fn load_from_path<P: AsRef<Path>>(path: P, object_types: &ObjectTypes) -> Result<Level, LevelError> {
+        use std::fs::File;
+        use std::io::Read;
+
+        let path = path.as_ref();
+
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) => return Err(LevelError::Io(format!("Could not open {}: {}", path.display(), e)))
+        };
+
+        let mut level_data = String::new();
+        match file.read_to_string(&mut level_data) {
+            Ok(_) => (),
+            Err(e) => return Err(LevelError::Io(format!("Could not read {}: {}", path.display(), e)))
+        };
+
+        Level::parse(path, &level_data, object_types)
+    }
+
+    /// Parses the contents of a level file. The path decides the format, as in `load_from_path`.
+    pub fn parse<P: AsRef<Path>>(path: P, level_data: &str, object_types: &ObjectTypes) -> Result<Level, LevelError> {
+        let is_tmx = match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some("tmx") => true,
+            _ => false
+        };
+
+        if is_tmx {
+            // External tilesets are found relative to the map
+            let dir = path.as_ref().parent().unwrap_or(Path::new(""));
+            parse_from_tmx(level_data, dir, object_types)
+        } else {
+            parse_from_json(level_data, object_types)
+        }
+    }
+
+    /// Writes the level out as a Tiled JSON map. The path has to end in `.json`.
+    pub fn save_to_path<P: AsRef<Path>>(&self, path: P) -> Result<(), LevelError> {
+        use std::fs::File;
+        use std::io::Write;
+
+        let path = path.as_ref();
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => (),
+            _ => return Err(LevelError::UnsupportedFormat(format!("{}", path.display())))
+        };
+        let level_data = format!("{}", export::to_json(self).pretty());
+
+        let mut file = match File::create(path) {
+            Ok(file) => file,
+            Err(e) => return Err(LevelError::Io(format!("Could not create {}: {}", path.display(), e)))
+        };
+
+        match file.write_all(level_data.as_bytes()) {
+            Ok(()) => Ok(()),
+            Err(e) => Err(LevelError::Io(format!("Could not write {}: {}", path.display(), e)))
+        }
+    }
+
+    pub fn get_tiles(&self) -> &Tiles { &self.tiles }
+
+    pub fn get_tiles_mut(&mut self) -> &mut Tiles { &mut self.tiles }
+
+    pub fn layers(&self) -> &[TileLayer] {
+        self.tiles.layers()
+    }
+
+    pub fn get_screen(&self) -> Screen {
+        Screen::new(self.width as f32 * self.tile_size, self.height as f32 * self.tile_size, self.tile_size)
+    }
+
+    pub fn level_size_as_u32(&self) -> (u32, u32) {
+        let tile_size = self.tile_size as u32;
+        (self.width * tile_size, self.height * tile_size)
+    }
+
+    fn trigger_set_to(&mut self, trigger: TriggerId) -> bool {
+        let mut did_something = false;
+        for set_to in self.set_tos.iter_mut().filter(|s| s.triggered_by == Some(trigger) && s.replaced.is_none()) {
+            let replaced = self.tiles.apply_set_to(set_to);
+            set_to.replaced = Some(replaced);
+            did_something = true;
+        }
+
+        did_something
+    }
+
+    /// Reverts the SetTos applied by `trigger_set_to`
+    fn release_set_to(&mut self, trigger: TriggerId) -> bool {
+        let mut did_something = false;
+        for set_to in self.set_tos.iter_mut().filter(|s| s.triggered_by == Some(trigger)) {
+            if let Some(replaced) = set_to.replaced.take() {
+                self.tiles.revert_set_to(set_to, replaced);
+                did_something = true;
+            }
+        }
+
+        did_something
+    }
+
+    /// Applies or reverts SetTos
+    pub fn signal_set_to(&mut self, signal: Signal) -> bool {
+        match signal {
+            Signal::On(trigger) => self.trigger_set_to(trigger),
+            Signal::Off(trigger) => self.release_set_to(trigger)
+        }
+    }
+
+    pub fn save_state(&self) -> LevelState {
+        LevelState {
+            tiles: self.tiles.clone(),
+            set_to_replaced: self.set_tos.iter().map(|s| s.replaced.clone()).collect()
+        }
+    }
+
+    /// Goes back to the state returned by `save_state`
+    pub fn restore_state(&mut self, state: &LevelState) {
+        self.tiles = state.tiles.clone();
+        for (set_to, replaced) in self.set_tos.iter_mut().zip(state.set_to_replaced.iter()) {
+            set_to.replaced = replaced.clone();
+        }
+    }
+}
+
+pub struct LevelTileIterator<'a> {
+    tiles: &'a Vec<Tile>,
+    width: usize,
+    index: usize
+}
+
+impl<'a> Iterator for LevelTileIterator<'a> {
+    type Item = (u32, u32, &'a Tile);
+
+    fn next(&mut self) -> Option<(u32, u32, &'a Tile)> {
+        if self.index >= self.tiles.len() {
+            None
+        } else {
+            let tile = &self.tiles[self.index];
+            let (x, y) = (self.index % self.width, self.index / self.width);
+            self.index += 1;
+
+            Some((x as u32, y as u32, tile))
+        }
+    }
+}
+
+/// A map as read from a Tiled file, before any game objects are made from it
+struct MapData {
+    width: u32,
+    height: u32,
+    tile_size: f32,
+    /// `#rrggbb` or `#aarrggbb`
+    background_color: Option<String>,
+    /// Tile and image layers in draw order, bottom first
+    layers: Vec<LayerData>,
+    tilesets: Vec<Tileset>,
+    tile_data: Vec<TileData>,
+    objects: Vec<ObjectData>,
+    /// The map's own custom properties
+    properties: rustc_serialize::json::Object
+}
+
+/// Fails unless every size is above zero, and the map's tiles can be counted without overflowing
+fn check_map_size(width: u32, height: u32, tile_size: u32) -> Result<(), LevelError> {
+    for &(key, value) in [("width", width), ("height", height), ("tilewidth", tile_size)].iter() {
+        if value == 0 {
+            return Err(LevelError::InvalidField(Location::Map, key, "must be greater than 0".to_string()));
+        }
+    }
+
+    if width.checked_mul(height).is_none() {
+        return Err(LevelError::InvalidField(Location::Map, "height", "makes the map too big".to_string()));
+    }
+
+    Ok(())
+}
+
+struct LayerData {
+    name: String,
+    contents: LayerContents,
+    /// Tiled's parallax factor for each axis. 1 scrolls along with the view.
+    parallax: (f32, f32),
+    properties: rustc_serialize::json::Object
+}
+
+enum LayerContents {
+    /// Global tile IDs, including the flip bits
+    Tiles(Vec<u32>),
+    /// The image file, which the game can't draw
+    Image(Option<String>)
+}
+
+/// A tile in a tileset that has custom properties
+struct TileData {
+    tileset: String,
+    /// Local to the tileset
+    id: u32,
+    gid: u32,
+    properties: rustc_serialize::json::Object
+}
+
+struct ObjectData {
+    layer: String,
+    index: usize,
+    typ: String,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    properties: rustc_serialize::json::Object
+}
+
+impl ObjectData {
+    fn location(&self) -> Location {
+        Location::Object {
+            layer: self.layer.clone(),
+            index: self.index,
+            typ: self.typ.clone()
+        }
+    }
+}
+
+fn parse_from_json(input: &str, object_types: &ObjectTypes) -> Result<Level, LevelError> {
+    build_level(try!(json::parse(input)), object_types)
+}
+
+fn parse_from_tmx(input: &str, dir: &Path, object_types: &ObjectTypes) -> Result<Level, LevelError> {
+    build_level(try!(tmx::parse(input, dir)), object_types)
+}
+
+fn build_level(map: MapData, object_types: &ObjectTypes) -> Result<Level, LevelError> {
+    let MapData { width, height, tile_size, background_color, layers: layer_data, tilesets, tile_data, objects, properties: map_properties } = map;
+
+    let keep_inventory = match try!(parse_property_as_string(&Location::Map, &map_properties, "inventory")) {
+        Some(ref rule) if rule == "keep" => true,
+        Some(ref rule) if rule == "reset" => false,
+        Some(_) => return Err(LevelError::InvalidProperty(Location::Map, "inventory".to_string(), "either keep or reset")),
+        None => true
+    };
+
+    let checkpoint_restore = match try!(parse_property_as_string(&Location::Map, &map_properties, "checkpoint_restore")) {
+        Some(name) => match CheckpointRestore::from_name(&name) {
+            Some(restore) => restore,
+            None => return Err(LevelError::InvalidProperty(Location::Map, "checkpoint_restore".to_string(), "nothing, doors or everything"))
+        },
+        None => CheckpointRestore::Nothing
+    };
+
+    let mut physics = Vec::new();
+    for key in map_properties.keys().filter(|k| k.starts_with(PHYSICS_PROPERTY_PREFIX)) {
+        let name = &key[PHYSICS_PROPERTY_PREFIX.len()..];
+        let value = try!(require_property(&Location::Map, key, parse_property_as_number(&Location::Map, &map_properties, key)));
+
+        match PlayerPhysics::new().set(name, value) {
+            Ok(()) => physics.push((name.to_string(), value)),
+            Err(SettingError::Unknown) => return Err(LevelError::InvalidProperty(Location::Map, key.clone(), "a player physics setting")),
+            Err(SettingError::Invalid(expected)) => return Err(LevelError::InvalidProperty(Location::Map, key.clone(), expected))
+        }
+    }
+
+    for tileset in tilesets.iter().filter(|t| !t.is_drawable()) {
+        println!("Warning: tileset '{}' doesn't use {}, so its tiles won't be drawn", tileset.name, GAME_TILESET_IMAGE);
+    }
+
+    let tile_types = try!(build_tile_types(tilesets, &tile_data));
+
+    let trigger_names = {
+        let declared: Vec<String> = match try!(parse_property_as_string(&Location::Map, &map_properties, "triggers")) {
+            Some(names) => names.split(',').map(|n| n.trim().to_string()).filter(|n| !n.is_empty()).collect(),
+            None => Vec::new()
+        };
+        try!(TriggerNames::new(&declared))
+    };
+
+    let background_color = match background_color {
+        Some(color) => match properties::parse_color(&color) {
+            Some(color) => Some(color),
+            None => return Err(LevelError::InvalidField(Location::Map, "backgroundcolor", format!("is not a color: {}", color)))
+        },
+        None => None
+    };
+
+    let mut layers = Vec::new();
+    let mut parallax_layers = Vec::new();
+
+    for layer in layer_data.iter() {
+        let loc = &Location::Layer(layer.name.clone());
+
+        let gids = match layer.contents {
+            LayerContents::Tiles(ref gids) => gids,
+            LayerContents::Image(_) => {
+                // The image itself can't be drawn, so it stands for a tile from the tileset
+                match try!(parse_property_as_number(loc, &layer.properties, "tile")) {
+                    Some(tile_id) => parallax_layers.push(ParallaxLayer {
+                        name: layer.name.clone(),
+                        source: ParallaxSource::Tile(try!(Tile::from_gid(loc, &tile_types, tile_id))),
+                        rate: layer.parallax
+                    }),
+                    None => println!("Warning: {}: image layer has no 'tile' property, leaving it out", loc)
+                }
+                continue;
+            }
+        };
+
+        let tiles_vec: Result<Vec<Tile>, LevelError> = gids.iter().map(|&value| Tile::from_gid(loc, &tile_types, value)).collect();
+        let tiles_vec = try!(tiles_vec);
+
+        if tiles_vec.len() != (width*height) as usize {
+            return Err(LevelError::WrongTileCount(loc.clone(), (width*height) as usize, tiles_vec.len()));
+        }
+
+        let mut tile_layer = TileLayer::new(layer.name.clone(), width, tiles_vec);
+
+        if layer.parallax != (1.0, 1.0) {
+            tile_layer.collides = false;
+            parallax_layers.push(ParallaxLayer {
+                name: layer.name.clone(),
+                source: ParallaxSource::Layer(tile_layer),
+                rate: layer.parallax
+            });
+            continue;
+        }
+
+        // Only the first layer collides unless told otherwise, so decoration layers don't need the property
+        tile_layer.collides = try!(parse_property_as_boolean(loc, &layer.properties, "collision")).unwrap_or(layers.is_empty());
+        tile_layer.foreground = try!(parse_property_as_boolean(loc, &layer.properties, "foreground")).unwrap_or(false);
+        layers.push(tile_layer);
+    }
+
+    if layers.is_empty() {
+        return Err(LevelError::MissingField(Location::Map, "layers"));
+    }
+
+    let layer_names: Vec<String> = layers.iter().map(|l| l.name.clone()).collect();
+
+    let mut level_objects = LevelObjects::new();
+
+    for object in objects.iter() {
+        let loc = &object.location();
+
+        let parser = match object_types.get(&object.typ) {
+            Some(parser) => parser,
+            None => match object_types.unknown {
+                UnknownObjects::Error => return Err(LevelError::UnknownObjectType(loc.clone())),
+                UnknownObjects::Warn => {
+                    println!("Warning: {}: unknown object type, leaving it out", loc);
+                    continue;
+                },
+                UnknownObjects::Ignore => continue
+            }
+        };
+
+        let context = ObjectContext {
+            typ: &object.typ,
+            x: object.x,
+            y: object.y,
+            width: object.width,
+            height: object.height,
+            tile_size: tile_size,
+            map_size: (width, height),
+            location: loc,
+            properties: &object.properties,
+            trigger_names: &trigger_names,
+            tile_types: &tile_types,
+            items: &object_types.items,
+            tile_layers: &layer_names
+        };
+
+        try!(parser(&context, &mut level_objects));
+    }
+
+    let LevelObjects {
+        player_start_pos, items, set_tos, gates, counters, timers
+    } = level_objects;
+
+    let tiles = Tiles::new(width, height, layers, tile_size);
+
+    Ok(Level {
+        width: width,
+        height: height,
+        tile_size: tile_size,
+        player_start_pos: player_start_pos,
+        keep_inventory: keep_inventory,
+        background_color: background_color,
+        physics: physics,
+        checkpoint_restore: checkpoint_restore,
+        parallax_layers: parallax_layers,
+        tiles: tiles,
+        tile_types: tile_types,
+        trigger_names: trigger_names,
+        items: PlacedItems(items),
+        set_tos: set_tos,
+        gates: gates,
+        counters: counters,
+        timers: timers
+    })
+}
+
+fn build_tile_types(tilesets: Vec<Tileset>, tile_data: &[TileData]) -> Result<TileTypes, LevelError> {
+    let mut tile_types = TileTypes {
+        tilesets: tilesets,
+        types: HashMap::new()
+    };
+
+    for tile in tile_data.iter() {
+        let loc = &Location::Tile { tileset: tile.tileset.clone(), id: tile.id };
+        let properties = &tile.properties;
+
+        let kind = match try!(parse_property_as_string(loc, properties, "kind")) {
+            Some(name) => match TileKind::from_name(&name) {
+                Some(kind) => kind,
+                None => return Err(LevelError::InvalidProperty(loc.clone(), "kind".to_string(), "one of coin, exit, door, dirt_entrance or ladder"))
+            },
+            None => TileKind::Normal
+        };
+        let shape = match try!(parse_property_as_string(loc, properties, "shape")) {
+            Some(name) => match TileShape::from_name(&name) {
+                Some(shape) => shape,
+                None => return Err(LevelError::InvalidProperty(loc.clone(), "shape".to_string(), "one of full, platform, slope, gentle_slope_low or gentle_slope_high"))
+            },
+            None => TileShape::Full
+        };
+        // Ladders are there to be climbed through
+        let is_blocking = try!(parse_property_as_boolean(loc, properties, "blocking")).unwrap_or(kind != TileKind::Ladder);
+        let can_dig = try!(parse_property_as_boolean(loc, properties, "diggable")).unwrap_or(false);
+
+        let atlas_id = tile_types.atlas_id(tile.gid);
+
+        tile_types.types.insert(tile.gid, TileType {
+            id: tile.gid,
+            atlas_id: atlas_id,
+            is_blocking: is_blocking,
+            can_dig: can_dig,
+            kind: kind,
+            shape: shape
+        });
+    }
+
+    Ok(tile_types)
+}
+
+/// Turns an absent property into an error
+fn require_property<T>(location: &Location, key: &str, value: Result<Option<T>, LevelError>) -> Result<T, LevelError> {
+    match try!(value) {
+        Some(v) => Ok(v),
+        None => Err(LevelError::MissingProperty(location.clone(), key.to_string()))
+    }
+}
+
+fn parse_tiles(location: &Location, properties: &rustc_serialize::json::Object, key: &str) -> Result<Vec<u16>, LevelError> {
+    let value_str = try!(require_property(location, key, parse_property_as_string(location, properties, key)));
+
+    let mut tiles = Vec::new();
+    for num_str in value_str.split(' ') {
+        match u16::from_str_radix(num_str, 16) {
+            Ok(tile) => tiles.push(tile),
+            Err(_) => return Err(LevelError::InvalidProperty(location.clone(), key.to_string(), "a list of hexadecimal tile IDs"))
+        }
+    }
+
+    Ok(tiles)
+}
+
+fn parse_property_as_string(location: &Location, properties: &rustc_serialize::json::Object, key: &str) -> Result<Option<String>, LevelError> {
+    match properties.get(key) {
+        Some(j) => match j.as_string() {
+            Some(value_str) => Ok(Some(value_str.to_string())),
+            None => Err(LevelError::InvalidProperty(location.clone(), key.to_string(), "a string"))
+        },
+        None => Ok(None)
+    }
+}
+
+/// Either a bool property, or the string "true" or "false"
+fn parse_property_as_boolean(location: &Location, properties: &rustc_serialize::json::Object, key: &str) -> Result<Option<bool>, LevelError> {
+    use rustc_serialize::json::Json;
+
+    match properties.get(key) {
+        Some(&Json::Boolean(value)) => Ok(Some(value)),
+        Some(&Json::String(ref value_str)) if value_str == "true" => Ok(Some(true)),
+        Some(&Json::String(ref value_str)) if value_str == "false" => Ok(Some(false)),
+        Some(_) => Err(LevelError::InvalidProperty(location.clone(), key.to_string(), "a bool (true or false)")),
+        None => Ok(None)
+    }
+}
+
+pub trait ParseNumber: Sized {
+    fn parse(str: &str, radix: u32) -> Option<Self>;
+
+    /// From an int or float property
+    fn from_json(json: &rustc_serialize::json::Json) -> Option<Self>;
+}
+
+impl ParseNumber for u8 {
+    fn parse(str: &str, radix: u32) -> Option<u8> {
+        u8::from_str_radix(str, radix).ok()
+    }
+
+    fn from_json(json: &rustc_serialize::json::Json) -> Option<u8> {
+        json.as_u64().and_then(|n| if n <= ::std::u8::MAX as u64 { Some(n as u8) } else { None })
+    }
+}
+
+impl ParseNumber for u16 {
+    fn parse(str: &str, radix: u32) -> Option<u16> {
+        u16::from_str_radix(str, radix).ok()
+    }
+
+    fn from_json(json: &rustc_serialize::json::Json) -> Option<u16> {
+        json.as_u64().and_then(|n| if n <= ::std::u16::MAX as u64 { Some(n as u16) } else { None })
+    }
+}
+
+impl ParseNumber for u32 {
+    fn parse(str: &str, radix: u32) -> Option<u32> {
+        u32::from_str_radix(str, radix).ok()
+    }
+
+    fn from_json(json: &rustc_serialize::json::Json) -> Option<u32> {
+        json.as_u64().and_then(|n| if n <= ::std::u32::MAX as u64 { Some(n as u32) } else { None })
+    }
+}
+
+impl ParseNumber for f32 {
+    fn parse(str: &str, _radix: u32) -> Option<f32> {
+        str.parse().ok()
+    }
+
+    fn from_json(json: &rustc_serialize::json::Json) -> Option<f32> {
+        json.as_f64().map(|n| n as f32)
+    }
+}
+
+/// Either an int or float property, or a string containing a number
+fn parse_property_as_number<T: ParseNumber>(location: &Location, properties: &rustc_serialize::json::Object, key: &str) -> Result<Option<T>, LevelError>
+{
+    use rustc_serialize::json::Json;
+
+    let value = match properties.get(key) {
+        Some(&Json::String(ref value_str)) => ParseNumber::parse(value_str, 10),
+        Some(json) => ParseNumber::from_json(json),
+        None => return Ok(None)
+    };
+
+    match value {
+        Some(value) => Ok(Some(value)),
+        None => Err(LevelError::InvalidProperty(location.clone(), key.to_string(), "a number in range"))
+    }
+}
+
+/// Trigger properties can be strings or ints
+fn parse_property_as_trigger_string(location: &Location, properties: &rustc_serialize::json::Object, key: &str) -> Result<Option<String>, LevelError>
+{
+    use rustc_serialize::json::Json;
+
+    match properties.get(key) {
+        Some(&Json::String(ref value_str)) => Ok(Some(value_str.clone())),
+        Some(&Json::I64(n)) => Ok(Some(n.to_string())),
+        Some(&Json::U64(n)) => Ok(Some(n.to_string())),
+        Some(_) => Err(LevelError::InvalidProperty(location.clone(), key.to_string(), "a trigger name or number")),
+        None => Ok(None)
+    }
+}
+
+/// A trigger number, or a name declared in the map's `triggers` property
+fn parse_property_as_trigger(location: &Location, properties: &rustc_serialize::json::Object, key: &str, names: &TriggerNames) -> Result<Option<TriggerId>, LevelError>
+{
+    match try!(parse_property_as_trigger_string(location, properties, key)) {
+        Some(name) => Ok(Some(try!(names.resolve(location, key, name.trim())))),
+        None => Ok(None)
+    }
+}
+
+/// A comma-separated list of trigger names or numbers
+fn parse_property_as_trigger_list(location: &Location, properties: &rustc_serialize::json::Object, key: &str, names: &TriggerNames) -> Result<Option<Vec<TriggerId>>, LevelError>
+{
+    match try!(parse_property_as_trigger_string(location, properties, key)) {
+        Some(value_str) => {
+            let mut ids = Vec::new();
+            for name in value_str.split(',') {
+                ids.push(try!(names.resolve(location, key, name.trim())));
+            }
+            Ok(Some(ids))
+        },
+        None => Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+    use super::{parse_from_json, parse_from_tmx, ObjectTypes, UnknownObjects, Tile, TileKind, TileLayer, TileShape, TileType, Tiles};
+    use super::super::rect::Rect;
+    use super::super::wrapping::Screen;
+
+    fn tile(c: char) -> Tile {
+        let (is_blocking, kind, shape, flip_x) = match c {
+            '#' => (true, TileKind::Normal, TileShape::Full, false),
+            '-' => (true, TileKind::Normal, TileShape::Platform, false),
+            '/' => (true, TileKind::Normal, TileShape::Slope, false),
+            '\\' => (true, TileKind::Normal, TileShape::Slope, true),
+            'H' => (false, TileKind::Ladder, TileShape::Full, false),
+            'D' => (true, TileKind::Door, TileShape::Full, false),
+            _ => return Tile::empty()
+        };
+
+        Tile {
+            tile_type: TileType {
+                id: 1,
+                atlas_id: None,
+                is_blocking: is_blocking,
+                can_dig: false,
+                kind: kind,
+                shape: shape
+            },
+            flip_x: flip_x,
+            flip_y: false,
+            flip_diagonal: false
+        }
+    }
+
+    /// 4x4 tiles of 16 pixels
+    fn tiles(rows: &[&str]) -> Tiles {
+        let tiles = rows.iter().flat_map(|row| row.chars()).map(tile).collect();
+        Tiles::new(4, 4, vec![TileLayer::new("tiles".to_string(), 4, tiles)], 16.0)
+    }
+
+    fn rect(x: f32, y: f32) -> Rect<f32> {
+        Rect::from_xywh(&Screen::new(64.0, 64.0, 16.0), x, y, 16.0, 16.0)
+    }
+
+    #[test]
+    fn platforms_only_block_from_above() {
+        let tiles = tiles(&["....", "....", "----", "####"]);
+
+        assert!(tiles.collision_walking(&rect(0.0, 17.0), (0.0, 2.0)) == Some((0.0, 16.0)));
+        // Jumping up through it
+        assert!(tiles.collision_walking(&rect(0.0, 30.0), (0.0, -2.0)) == None);
+        // Falling while already inside it
+        assert!(tiles.collision_walking(&rect(0.0, 20.0), (0.0, 2.0)) == None);
+        // Walking through it
+        assert!(tiles.collision_walking(&rect(2.0, 20.0), (2.0, 0.0)) == None);
+    }
+
+    #[test]
+    fn slopes_lift_the_player() {
+        let rising_right = tiles(&["....", "....", "./..", "####"]);
+        assert!(rising_right.collision_walking(&rect(2.0, 32.0), (2.0, 0.0)) == Some((2.0, 30.5)));
+
+        let flipped = tiles(&["....", "....", ".\\..", "####"]);
+        assert!(flipped.collision_walking(&rect(30.0, 32.0), (-2.0, 0.0)) == Some((30.0, 30.5)));
+    }
+
+    #[test]
+    fn slopes_block_from_their_high_side() {
+        let tiles = tiles(&["....", "....", "./..", "####"]);
+
+        assert!(tiles.collision_walking(&rect(30.0, 32.0), (-2.0, 0.0)) == Some((32.0, 32.0)));
+    }
+
+    #[test]
+    fn ladders() {
+        let tiles = tiles(&["....", ".H..", ".H..", "####"]);
+
+        // The top of the ladder can be stood on
+        assert!(tiles.collision_walking(&rect(16.0, 1.0), (0.0, 2.0)) == Some((16.0, 0.0)));
+        // The rest of it can't
+        assert!(tiles.collision_walking(&rect(16.0, 18.0), (0.0, 2.0)) == None);
+
+        let ladder = tiles.ladder_at(&rect(16.0, 32.0)).unwrap();
+        assert!(ladder.left_top().xy() == (16.0, 0.0));
+        assert!(ladder.size() == (16.0, 48.0));
+
+        assert!(tiles.ladder_at(&rect(48.0, 32.0)).is_none());
+    }
+
+    #[test]
+    fn ladders_wrap_around() {
+        let tiles = tiles(&["H...", "....", "....", "H###"]);
+
+        // The middle of the rect is past the right edge, and the ladder goes on past the bottom
+        let ladder = tiles.ladder_at(&rect(56.0, 48.0)).unwrap();
+        assert!(ladder.left_top().xy() == (0.0, 32.0));
+        assert!(ladder.size() == (16.0, 48.0));
+    }
+
+    #[test]
+    fn relock_doors() {
+        let mut tiles = tiles(&["D...", "....", "..D.", "####"]);
+        let doors = tiles.doors();
+        assert!(doors.len() == 2);
+
+        tiles.remove_key_entrance(0, 0);
+        assert!(tiles.doors().len() == 1);
+
+        assert!(tiles.relock_doors(&doors));
+        assert!(tiles.get_tile(0, 0).tile_type.kind == TileKind::Door);
+        assert!(tiles.doors().len() == 2);
+
+        // Nothing left to relock
+        assert!(!tiles.relock_doors(&doors));
+    }
+
+    #[test]
+    fn gids_past_a_u16() {
+        let level = match parse_from_json(r##"{
+            "width": 1, "height": 1, "tilewidth": 16, "tileheight": 16,
+            "tilesets": [{ "firstgid": 1, "name": "tileset" }, { "firstgid": 70000, "name": "big", "tileproperties": { "1": { "kind": "coin" } } }],
+            "layers": [{ "name": "tiles", "data": [70001] }]
+        }"##, &ObjectTypes::builtin(UnknownObjects::Error)) {
+            Ok(level) => level,
+            Err(e) => panic!("{}", e)
+        };
+
+        let tile_type = &level.get_tiles().get_tile(0, 0).tile_type;
+        assert!(tile_type.id == 70001);
+        assert!(tile_type.kind == TileKind::Coin);
+    }
+
+    #[test]
+    fn gids_outside_the_tilesets() {
+        let level = parse_from_json(r##"{
+            "width": 1, "height": 1, "tilewidth": 16, "tileheight": 16,
+            "tilesets": [{ "firstgid": 5, "name": "tileset" }],
+            "layers": [{ "name": "tiles", "data": [3] }]
+        }"##, &ObjectTypes::builtin(UnknownObjects::Error));
+
+        assert!(level.is_err());
+    }
+
+    fn json_with_size(width: &str, height: &str, tile_size: &str) -> String {
+        format!(r##"{{
+            "width": {}, "height": {}, "tilewidth": {}, "tileheight": {},
+            "tilesets": [{{ "firstgid": 1, "name": "tileset" }}],
+            "layers": [{{ "name": "tiles", "data": [0] }}]
+        }}"##, width, height, tile_size, tile_size)
+    }
+
+    #[test]
+    fn map_sizes_that_cant_be_used() {
+        let object_types = ObjectTypes::builtin(UnknownObjects::Error);
+        assert!(parse_from_json(&json_with_size("1", "1", "16"), &object_types).is_ok());
+
+        assert!(parse_from_json(&json_with_size("0", "1", "16"), &object_types).is_err());
+        assert!(parse_from_json(&json_with_size("1", "0", "16"), &object_types).is_err());
+        assert!(parse_from_json(&json_with_size("1", "1", "0"), &object_types).is_err());
+        assert!(parse_from_json(&json_with_size("-1", "1", "16"), &object_types).is_err());
+        assert!(parse_from_json(&json_with_size("1.5", "1", "16"), &object_types).is_err());
+        assert!(parse_from_json(&json_with_size("4294967297", "1", "16"), &object_types).is_err());
+        // Each fits, but there are more tiles than a u32 can count
+        assert!(parse_from_json(&json_with_size("65536", "65536", "16"), &object_types).is_err());
+
+        let tmx = r##"<?xml version="1.0" encoding="UTF-8"?>
+            <map version="1.0" orientation="orthogonal" width="0" height="1" tilewidth="16" tileheight="16">
+                <tileset firstgid="1" name="tileset"/>
+                <layer name="tiles" width="0" height="1"><data encoding="csv">0</data></layer>
+            </map>"##;
+        assert!(parse_from_tmx(tmx, Path::new(""), &object_types).is_err());
+    }
+
+    fn json_with_set_to(x: i32, y: i32, width: u32, height: u32) -> String {
+        format!(r##"{{
+            "width": 2, "height": 2, "tilewidth": 16, "tileheight": 16,
+            "tilesets": [{{ "firstgid": 1, "name": "tileset" }}],
+            "layers": [
+                {{ "name": "tiles", "data": [0, 0, 0, 0] }},
+                {{ "name": "objects", "objects": [
+                    {{ "type": "setto", "x": {}, "y": {}, "width": {}, "height": {},
+                      "properties": {{ "tile": "1" }} }}
+                ] }}
+            ]
+        }}"##, x, y, width, height)
+    }
+
+    #[test]
+    fn set_tos_outside_the_map() {
+        let object_types = ObjectTypes::builtin(UnknownObjects::Error);
+        assert!(parse_from_json(&json_with_set_to(0, 0, 32, 32), &object_types).is_ok());
+        assert!(parse_from_json(&json_with_set_to(16, 16, 16, 16), &object_types).is_ok());
+
+        assert!(parse_from_json(&json_with_set_to(16, 0, 32, 16), &object_types).is_err());
+        assert!(parse_from_json(&json_with_set_to(0, 16, 16, 32), &object_types).is_err());
+        assert!(parse_from_json(&json_with_set_to(-16, 0, 16, 16), &object_types).is_err());
+        assert!(parse_from_json(&json_with_set_to(0, -16, 16, 16), &object_types).is_err());
+    }
+}
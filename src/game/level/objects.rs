@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use rustc_serialize::json;
+use super::{LevelError, Location, ParseNumber, TileTypes, Tile, TriggerId, SetTo};
+use super::{Gate, GateKind, Counter, Timer};
+use super::{require_property, parse_tiles, parse_property_as_string, parse_property_as_boolean, parse_property_as_number};
+use super::{parse_property_as_trigger, parse_property_as_trigger_list};
+use super::super::inventory::ItemTypes;
+use super::super::items::{self, PlacedItem};
+use super::triggers::TriggerNames;
+
+/// Reads one object from the level file and adds it to `objects`
+pub type ObjectParser = fn(&ObjectContext, &mut LevelObjects) -> Result<(), LevelError>;
+
+/// What to do with objects whose type isn't registered
+#[derive(Copy, Clone, PartialEq)]
+pub enum UnknownObjects {
+    /// Fail to load the level
+    Error,
+    /// Print a warning and leave the object out
+    Warn,
+    /// Quietly leave the object out
+    Ignore
+}
+
+impl UnknownObjects {
+    pub fn from_name(name: &str) -> Option<UnknownObjects> {
+        match name {
+            "error" => Some(UnknownObjects::Error),
+            "warn" => Some(UnknownObjects::Warn),
+            "ignore" => Some(UnknownObjects::Ignore),
+            _ => None
+        }
+    }
+}
+
+/// Maps the `type` of Tiled objects to the parsers that read them
+pub struct ObjectTypes {
+    parsers: HashMap<String, ObjectParser>,
+    pub unknown: UnknownObjects,
+    /// The items that chests can contain
+    pub items: ItemTypes
+}
+
+impl ObjectTypes {
+    /// No object types or items at all
+    pub fn new(unknown: UnknownObjects) -> ObjectTypes {
+        ObjectTypes {
+            parsers: HashMap::new(),
+            unknown: unknown,
+            items: ItemTypes::new()
+        }
+    }
+
+    /// Every object type and item the game knows about
+    pub fn builtin(unknown: UnknownObjects) -> ObjectTypes {
+        let mut types = ObjectTypes::new(unknown);
+        types.items = ItemTypes::builtin();
+        types.register("player", parse_player);
+        types.register("switch", items::switch::parse);
+        types.register("chest", items::chest::parse);
+        types.register("beanstalk", items::beanstalk::parse);
+        types.register("monster1", items::monster::parse_monster1);
+        types.register("monster2", items::monster::parse_monster2);
+        types.register("stickykey", items::key::parse);
+        types.register("checkpoint", items::checkpoint::parse);
+        types.register("message", items::message::parse);
+        types.register("setto", parse_set_to);
+        types.register("and", parse_gate);
+        types.register("or", parse_gate);
+        types.register("not", parse_gate);
+        types.register("counter", parse_counter);
+        types.register("timer", parse_timer);
+        types
+    }
+
+    /// Replaces any parser already registered for `typ`
+    pub fn register(&mut self, typ: &str, parser: ObjectParser) {
+        self.parsers.insert(typ.to_string(), parser);
+    }
+
+    pub fn get(&self, typ: &str) -> Option<ObjectParser> {
+        self.parsers.get(typ).map(|&parser| parser)
+    }
+}
+
+/// Everything a parser gets to know about the object it's reading
+pub struct ObjectContext<'a> {
+    pub typ: &'a str,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub tile_size: f32,
+    /// The map's width and height, in tiles
+    pub map_size: (u32, u32),
+    pub location: &'a Location,
+    pub properties: &'a json::Object,
+    pub trigger_names: &'a TriggerNames,
+    pub tile_types: &'a TileTypes,
+    pub items: &'a ItemTypes,
+    /// The names of the map's tile layers, bottom first
+    pub tile_layers: &'a [String]
+}
+
+impl<'a> ObjectContext<'a> {
+    pub fn string(&self, key: &str) -> Result<Option<String>, LevelError> {
+        parse_property_as_string(self.location, self.properties, key)
+    }
+
+    /// Either a bool property, or the string "true" or "false"
+    pub fn boolean(&self, key: &str) -> Result<Option<bool>, LevelError> {
+        parse_property_as_boolean(self.location, self.properties, key)
+    }
+
+    /// Either an int or float property, or a string containing a number
+    pub fn number<T: ParseNumber>(&self, key: &str) -> Result<Option<T>, LevelError> {
+        parse_property_as_number(self.location, self.properties, key)
+    }
+
+    /// A trigger number, or a name declared in the map's `triggers` property
+    pub fn trigger(&self, key: &str) -> Result<Option<TriggerId>, LevelError> {
+        parse_property_as_trigger(self.location, self.properties, key, self.trigger_names)
+    }
+
+    /// A comma-separated list of trigger names or numbers
+    pub fn triggers(&self, key: &str) -> Result<Option<Vec<TriggerId>>, LevelError> {
+        parse_property_as_trigger_list(self.location, self.properties, key, self.trigger_names)
+    }
+
+    /// A required list of hexadecimal tile IDs
+    pub fn tiles(&self, key: &str) -> Result<Vec<u16>, LevelError> {
+        parse_tiles(self.location, self.properties, key)
+    }
+
+    /// Fails if the property read into `value` is missing
+    pub fn require<T>(&self, key: &str, value: Result<Option<T>, LevelError>) -> Result<T, LevelError> {
+        require_property(self.location, key, value)
+    }
+}
+
+/// The objects read so far. They end up in the `Level`.
+pub struct LevelObjects {
+    pub player_start_pos: (f32, f32),
+    /// Everything the player meets in the world, in the order it was read
+    pub items: Vec<Box<PlacedItem>>,
+    pub set_tos: Vec<SetTo>,
+    pub gates: Vec<Gate>,
+    pub counters: Vec<Counter>,
+    pub timers: Vec<Timer>
+}
+
+impl LevelObjects {
+    pub fn new() -> LevelObjects {
+        LevelObjects {
+            player_start_pos: (0.0, 0.0),
+            items: Vec::new(),
+            set_tos: Vec::new(),
+            gates: Vec::new(),
+            counters: Vec::new(),
+            timers: Vec::new()
+        }
+    }
+}
+
+fn parse_player(o: &ObjectContext, objects: &mut LevelObjects) -> Result<(), LevelError> {
+    objects.player_start_pos = (o.x, o.y);
+
+    Ok(())
+}
+
+fn parse_set_to(o: &ObjectContext, objects: &mut LevelObjects) -> Result<(), LevelError> {
+    let tile_size = o.tile_size;
+
+    let triggered_by = try!(o.trigger("triggered_by"));
+
+    let tile_id = try!(o.require("tile", o.number("tile")));
+    let tile_type = match o.tile_types.get(tile_id) {
+        Some(tile_type) => tile_type,
+        None => return Err(LevelError::UnknownTile(o.location.clone(), tile_id))
+    };
+
+    let layer = match try!(o.string("layer")) {
+        Some(name) => match o.tile_layers.iter().position(|l| *l == name) {
+            Some(index) => Some(index),
+            None => return Err(LevelError::InvalidProperty(o.location.clone(), "layer".to_string(), "the name of a tile layer"))
+        },
+        None => None
+    };
+
+    if o.x < 0.0 {
+        return Err(LevelError::InvalidField(o.location.clone(), "x", "must not be negative".to_string()));
+    }
+    if o.y < 0.0 {
+        return Err(LevelError::InvalidField(o.location.clone(), "y", "must not be negative".to_string()));
+    }
+
+    let tile_x = (o.x / tile_size) as u32;
+    let tile_y = (o.y / tile_size) as u32;
+    let w = (o.width / tile_size) as u32;
+    let h = (o.height / tile_size) as u32;
+
+    // The region is set tile by tile, so it has to fit inside the map
+    let (map_width, map_height) = o.map_size;
+    if tile_x as u64 + w as u64 > map_width as u64 {
+        return Err(LevelError::InvalidField(o.location.clone(), "width", "reaches past the right edge of the map".to_string()));
+    }
+    if tile_y as u64 + h as u64 > map_height as u64 {
+        return Err(LevelError::InvalidField(o.location.clone(), "height", "reaches past the bottom of the map".to_string()));
+    }
+
+    objects.set_tos.push(SetTo {
+        x: tile_x,
+        y: tile_y,
+        width: w,
+        height: h,
+        tile: Tile {
+            tile_type: tile_type,
+            flip_x: false,
+            flip_y: false,
+            flip_diagonal: false
+        },
+        layer: layer,
+        triggered_by: triggered_by,
+        replaced: None
+    });
+
+    Ok(())
+}
+
+fn parse_gate(o: &ObjectContext, objects: &mut LevelObjects) -> Result<(), LevelError> {
+    let kind = match o.typ {
+        "and" => GateKind::And,
+        "or" => GateKind::Or,
+        _ => GateKind::Not
+    };
+    let inputs = try!(o.require("inputs", o.triggers("inputs")));
+    let trigger = try!(o.require("trigger", o.trigger("trigger")));
+
+    objects.gates.push(Gate {
+        kind: kind,
+        inputs: inputs,
+        trigger: trigger
+    });
+
+    Ok(())
+}
+
+fn parse_counter(o: &ObjectContext, objects: &mut LevelObjects) -> Result<(), LevelError> {
+    let inputs = try!(o.require("inputs", o.triggers("inputs")));
+    let count = try!(o.require("count", o.number("count")));
+    let trigger = try!(o.require("trigger", o.trigger("trigger")));
+
+    objects.counters.push(Counter {
+        inputs: inputs,
+        count: count,
+        trigger: trigger
+    });
+
+    Ok(())
+}
+
+fn parse_timer(o: &ObjectContext, objects: &mut LevelObjects) -> Result<(), LevelError> {
+    let inputs = try!(o.require("inputs", o.triggers("inputs")));
+    let delay = try!(o.require("delay", o.number("delay")));
+    let trigger = try!(o.require("trigger", o.trigger("trigger")));
+
+    objects.timers.push(Timer {
+        inputs: inputs,
+        delay: delay,
+        trigger: trigger
+    });
+
+    Ok(())
+}
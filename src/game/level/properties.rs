@@ -0,0 +1,140 @@
+use rustc_serialize::json::{Json, Object};
+use super::{LevelError, Location};
+
+// Tiled used to store every custom property as a string.
+// Newer versions give each property a type (int, float, bool, string, color or file),
+// which we keep by storing the value as the matching kind of JSON value.
+// Untyped properties stay strings.
+
+/// Converts the `value` attribute of a TMX `<property>` according to its `type` attribute
+pub fn from_tmx(location: &Location, name: &str, typ: Option<&str>, value: &str) -> Result<Json, LevelError> {
+    let mismatch = |expected| Err(LevelError::InvalidProperty(location.clone(), name.to_string(), expected));
+
+    match typ {
+        None | Some("string") | Some("file") => Ok(Json::String(value.to_string())),
+        Some("int") => match value.parse() {
+            Ok(n) => Ok(Json::I64(n)),
+            Err(_) => mismatch("an int")
+        },
+        Some("float") => match value.parse() {
+            Ok(n) => Ok(Json::F64(n)),
+            Err(_) => mismatch("a float")
+        },
+        Some("bool") => match value {
+            "true" => Ok(Json::Boolean(true)),
+            "false" => Ok(Json::Boolean(false)),
+            _ => mismatch("a bool")
+        },
+        Some("color") => {
+            if is_color(value) { Ok(Json::String(value.to_string())) } else { mismatch("a color") }
+        },
+        Some(_) => mismatch("of a supported type (int, float, bool, string, color or file)")
+    }
+}
+
+/// Checks a JSON property value against its declared type
+pub fn from_json(location: &Location, name: &str, typ: Option<&str>, value: &Json) -> Result<Json, LevelError> {
+    let mismatch = |expected| Err(LevelError::InvalidProperty(location.clone(), name.to_string(), expected));
+
+    match typ {
+        None => Ok(value.clone()),
+        Some("string") | Some("file") => match value.as_string() {
+            Some(s) => Ok(Json::String(s.to_string())),
+            None => mismatch("a string")
+        },
+        Some("int") => match value.as_i64() {
+            Some(n) => Ok(Json::I64(n)),
+            None => mismatch("an int")
+        },
+        Some("float") => match value.as_f64() {
+            Some(n) => Ok(Json::F64(n)),
+            None => mismatch("a float")
+        },
+        Some("bool") => match value.as_boolean() {
+            Some(b) => Ok(Json::Boolean(b)),
+            None => mismatch("a bool")
+        },
+        Some("color") => match value.as_string() {
+            Some(s) if is_color(s) => Ok(Json::String(s.to_string())),
+            _ => mismatch("a color")
+        },
+        Some(_) => mismatch("of a supported type (int, float, bool, string, color or file)")
+    }
+}
+
+/// Reads the `properties` of a JSON map, layer, object or tile.
+///
+/// Older Tiled versions write an object of names to values, with the types in
+/// a separate object (`types_key`). Newer ones write an array of
+/// `{"name", "type", "value"}` objects.
+pub fn read_json(location: &Location, owner: &Object, key: &'static str, types_key: &'static str) -> Result<Object, LevelError> {
+    let mut properties = Object::new();
+
+    match owner.get(key) {
+        None => (),
+        Some(&Json::Object(ref values)) => {
+            let types = match owner.get(types_key) {
+                Some(&Json::Object(ref types)) => Some(types),
+                Some(_) => return Err(LevelError::InvalidField(location.clone(), types_key, "is not a JSON object".to_string())),
+                None => None
+            };
+
+            for (name, value) in values.iter() {
+                let typ = types.and_then(|t| t.get(name)).and_then(|t| t.as_string());
+                properties.insert(name.clone(), try!(from_json(location, name, typ, value)));
+            }
+        },
+        Some(&Json::Array(ref list)) => {
+            for property in list.iter() {
+                let property = match property.as_object() {
+                    Some(p) => p,
+                    None => return Err(LevelError::InvalidField(location.clone(), key, "must only contain JSON objects".to_string()))
+                };
+
+                let name = match property.get("name").and_then(|n| n.as_string()) {
+                    Some(name) => name,
+                    None => return Err(LevelError::InvalidField(location.clone(), key, "has a property without a name".to_string()))
+                };
+                let typ = property.get("type").and_then(|t| t.as_string());
+                let value = match property.get("value") {
+                    Some(value) => value,
+                    None => return Err(LevelError::MissingProperty(location.clone(), name.to_string()))
+                };
+
+                properties.insert(name.to_string(), try!(from_json(location, name, typ, value)));
+            }
+        },
+        Some(_) => return Err(LevelError::InvalidField(location.clone(), key, "is not a JSON object or array".to_string()))
+    }
+
+    Ok(properties)
+}
+
+/// "#rrggbb" or "#aarrggbb"
+fn is_color(value: &str) -> bool {
+    value.starts_with("#") && (value.len() == 7 || value.len() == 9) && value[1..].chars().all(|c| c.is_digit(16))
+}
+
+/// Reads a color in Tiled's format as (red, green, blue, alpha)
+pub fn parse_color(value: &str) -> Option<(u8, u8, u8, u8)> {
+    if !is_color(value) {
+        return None;
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&value[i..i+2], 16).unwrap();
+
+    if value.len() == 7 {
+        Some((channel(1), channel(3), channel(5), 0xFF))
+    } else {
+        Some((channel(3), channel(5), channel(7), channel(1)))
+    }
+}
+
+/// The opposite of `parse_color`
+pub fn format_color((r, g, b, a): (u8, u8, u8, u8)) -> String {
+    if a == 0xFF {
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    } else {
+        format!("#{:02x}{:02x}{:02x}{:02x}", a, r, g, b)
+    }
+}
@@ -0,0 +1,437 @@
+use std::path::Path;
+use rustc_serialize::json::{Json, Object};
+use xml::attribute::OwnedAttribute;
+use xml::reader::EventReader;
+use xml::reader::events::XmlEvent;
+use super::{check_map_size, MapData, LayerData, LayerContents, ObjectData, TileData, Tileset, LevelError, Location};
+use super::properties;
+
+/// Where we are in the TMX document
+#[derive(Copy, Clone)]
+enum Section {
+    Map,
+    Tileset,
+    TileDef,
+    TileLayer,
+    TileData,
+    ImageLayer,
+    ObjectGroup,
+    Object,
+    /// A hidden layer or object group
+    Ignored
+}
+
+/// Parses a TMX map. External tilesets are loaded from `dir`, the directory the map is in.
+pub fn parse(input: &str, dir: &Path) -> Result<MapData, LevelError> {
+    let mut parser = EventReader::new(input.as_bytes());
+
+    let mut size: Option<(u32, u32, f32)> = None;
+    let mut background_color: Option<String> = None;
+    let mut layers: Vec<LayerData> = Vec::new();
+    let mut has_tiles = false;
+    let mut objects: Vec<ObjectData> = Vec::new();
+
+    let mut section = Section::Map;
+    let mut data_format: (Option<String>, Option<String>) = (None, None);
+    let mut data_text = String::new();
+    let mut data_gids: Vec<u32> = Vec::new();
+    let mut layer_name = String::new();
+    let mut layer_gids: Option<Vec<u32>> = None;
+    let mut layer_image: Option<String> = None;
+    let mut layer_parallax = (1.0, 1.0);
+    let mut layer_properties = Object::new();
+    let mut group_objects: Vec<ObjectData> = Vec::new();
+    let mut tilesets: Vec<Tileset> = Vec::new();
+    let mut tile_data: Vec<TileData> = Vec::new();
+    let mut map_properties = Object::new();
+
+    for event in parser.events() {
+        match event {
+            XmlEvent::StartElement { name, attributes, .. } => {
+                match (section, &name.local_name as &str) {
+                    (Section::Map, "map") => {
+                        let map = &Location::Map;
+                        let width: u32 = try!(require_attribute(map, &attributes, "width"));
+                        let height: u32 = try!(require_attribute(map, &attributes, "height"));
+                        let tile_width: u32 = try!(require_attribute(map, &attributes, "tilewidth"));
+                        let tile_height: u32 = try!(require_attribute(map, &attributes, "tileheight"));
+
+                        // Only square tiles are supported
+                        if tile_width != tile_height {
+                            return Err(LevelError::InvalidField(map.clone(), "tileheight", "must equal 'tilewidth'".to_string()));
+                        }
+                        try!(check_map_size(width, height, tile_width));
+
+                        size = Some((width, height, tile_width as f32));
+                        background_color = get_attribute(&attributes, "backgroundcolor");
+                    },
+                    (Section::Map, "property") => {
+                        let (name, value) = try!(read_property(&Location::Map, &attributes));
+                        map_properties.insert(name, value);
+                    },
+                    (Section::Map, "tileset") => {
+                        let first_gid = try!(require_attribute(&Location::Map, &attributes, "firstgid"));
+                        match get_attribute(&attributes, "source") {
+                            Some(source) => {
+                                let (tileset, tiles) = try!(load_tileset(&dir.join(&source), first_gid));
+                                tilesets.push(tileset);
+                                tile_data.extend(tiles.into_iter());
+                            },
+                            None => tilesets.push(new_tileset(&attributes, first_gid))
+                        }
+                        section = Section::Tileset;
+                    },
+                    (Section::Tileset, "image") => {
+                        try!(read_tileset_image(tilesets.last_mut().unwrap(), &attributes));
+                    },
+                    (Section::Tileset, "tile") => {
+                        tile_data.push(try!(read_tile(tilesets.last().unwrap(), &attributes)));
+                        section = Section::TileDef;
+                    },
+                    (Section::TileDef, "property") => {
+                        let tile = tile_data.last_mut().unwrap();
+                        let loc = Location::Tile { tileset: tile.tileset.clone(), id: tile.id };
+
+                        let (name, value) = try!(read_property(&loc, &attributes));
+                        tile.properties.insert(name, value);
+                    },
+                    (Section::Map, "layer") => {
+                        layer_name = get_attribute(&attributes, "name").unwrap_or(String::new());
+                        layer_gids = None;
+                        layer_properties = Object::new();
+                        layer_parallax = try!(read_parallax(&Location::Layer(layer_name.clone()), &attributes));
+                        section = if is_visible(&attributes) { Section::TileLayer } else { Section::Ignored };
+                    },
+                    (Section::Map, "imagelayer") => {
+                        layer_name = get_attribute(&attributes, "name").unwrap_or(String::new());
+                        layer_image = None;
+                        layer_properties = Object::new();
+                        layer_parallax = try!(read_parallax(&Location::Layer(layer_name.clone()), &attributes));
+                        section = if is_visible(&attributes) { Section::ImageLayer } else { Section::Ignored };
+                    },
+                    (Section::ImageLayer, "image") => {
+                        layer_image = get_attribute(&attributes, "source");
+                    },
+                    (Section::TileLayer, "property") | (Section::ImageLayer, "property") => {
+                        let (name, value) = try!(read_property(&Location::Layer(layer_name.clone()), &attributes));
+                        layer_properties.insert(name, value);
+                    },
+                    (Section::Map, "objectgroup") => {
+                        layer_name = get_attribute(&attributes, "name").unwrap_or(String::new());
+                        group_objects.clear();
+                        section = if is_visible(&attributes) { Section::ObjectGroup } else { Section::Ignored };
+                    },
+                    (Section::TileLayer, "data") => {
+                        data_format = (get_attribute(&attributes, "encoding"), get_attribute(&attributes, "compression"));
+                        data_text.clear();
+                        data_gids.clear();
+                        section = Section::TileData;
+                    },
+                    (Section::TileData, "tile") => {
+                        // Plain XML encoding: one element per tile
+                        let loc = &Location::Layer(layer_name.clone());
+                        data_gids.push(try!(parse_attribute(loc, &attributes, "gid")).unwrap_or(0));
+                    },
+                    (Section::ObjectGroup, "object") => {
+                        let typ = get_attribute(&attributes, "type").unwrap_or(String::new());
+                        let index = group_objects.len();
+                        let loc = &Location::Object {
+                            layer: layer_name.clone(),
+                            index: index,
+                            typ: typ.clone()
+                        };
+
+                        group_objects.push(ObjectData {
+                            layer: layer_name.clone(),
+                            index: index,
+                            typ: typ,
+                            x: try!(parse_attribute(loc, &attributes, "x")).unwrap_or(0.0),
+                            y: try!(parse_attribute(loc, &attributes, "y")).unwrap_or(0.0),
+                            width: try!(parse_attribute(loc, &attributes, "width")).unwrap_or(0.0),
+                            height: try!(parse_attribute(loc, &attributes, "height")).unwrap_or(0.0),
+                            properties: Object::new()
+                        });
+                        section = Section::Object;
+                    },
+                    (Section::Object, "property") => {
+                        let object = group_objects.last_mut().unwrap();
+
+                        let (name, value) = try!(read_property(&object.location(), &attributes));
+                        object.properties.insert(name, value);
+                    },
+                    _ => ()
+                }
+            },
+            XmlEvent::EndElement { name } => {
+                match (section, &name.local_name as &str) {
+                    (Section::Tileset, "tileset") => section = Section::Map,
+                    (Section::TileDef, "tile") => section = Section::Tileset,
+                    (Section::TileLayer, "layer") => {
+                        let loc = Location::Layer(layer_name.clone());
+                        let gids = match layer_gids.take() {
+                            Some(gids) => gids,
+                            None => return Err(LevelError::MissingField(loc, "data"))
+                        };
+                        layers.push(LayerData {
+                            name: layer_name.clone(),
+                            contents: LayerContents::Tiles(gids),
+                            parallax: layer_parallax,
+                            properties: ::std::mem::replace(&mut layer_properties, Object::new())
+                        });
+                        has_tiles = true;
+                        section = Section::Map;
+                    },
+                    (Section::ImageLayer, "imagelayer") => {
+                        layers.push(LayerData {
+                            name: layer_name.clone(),
+                            contents: LayerContents::Image(layer_image.take()),
+                            parallax: layer_parallax,
+                            properties: ::std::mem::replace(&mut layer_properties, Object::new())
+                        });
+                        section = Section::Map;
+                    },
+                    (Section::TileData, "data") => {
+                        let loc = Location::Layer(layer_name.clone());
+                        let gids = match data_format {
+                            (None, None) => data_gids.clone(),
+                            (Some(ref encoding), ref compression) => {
+                                try!(decode_data(&loc, &data_text, encoding, compression.as_ref().map(|c| &c[..])))
+                            },
+                            (None, Some(_)) => return Err(LevelError::MissingField(loc, "encoding"))
+                        };
+                        layer_gids = Some(gids);
+                        section = Section::TileLayer;
+                    },
+                    (Section::ObjectGroup, "objectgroup") => {
+                        objects.extend(::std::mem::replace(&mut group_objects, Vec::new()).into_iter());
+                        section = Section::Map;
+                    },
+                    (Section::Object, "object") => section = Section::ObjectGroup,
+                    (Section::Ignored, "layer") => section = Section::Map,
+                    (Section::Ignored, "objectgroup") => section = Section::Map,
+                    (Section::Ignored, "imagelayer") => section = Section::Map,
+                    _ => ()
+                }
+            },
+            XmlEvent::Characters(text) => {
+                if let Section::TileData = section {
+                    data_text.push_str(&text);
+                }
+            },
+            XmlEvent::Error(e) => return Err(LevelError::Syntax(format!("{}", e))),
+            _ => ()
+        }
+    }
+
+    let (width, height, tile_size) = match size {
+        Some(size) => size,
+        None => return Err(LevelError::MissingField(Location::Map, "map"))
+    };
+    if !has_tiles {
+        return Err(LevelError::MissingField(Location::Map, "layer"));
+    }
+
+    Ok(MapData {
+        width: width,
+        height: height,
+        tile_size: tile_size,
+        background_color: background_color,
+        layers: layers,
+        tilesets: tilesets,
+        tile_data: tile_data,
+        objects: objects,
+        properties: map_properties
+    })
+}
+
+fn decode_data(location: &Location, text: &str, encoding: &str, compression: Option<&str>) -> Result<Vec<u32>, LevelError> {
+    match encoding {
+        "csv" => {
+            let mut gids = Vec::new();
+            for num_str in text.split(',') {
+                match num_str.trim().parse() {
+                    Ok(gid) => gids.push(gid),
+                    Err(_) => return Err(LevelError::InvalidField(location.clone(), "data", format!("has an invalid tile: {}", num_str.trim())))
+                }
+            }
+            Ok(gids)
+        },
+        "base64" => {
+            use rustc_serialize::base64::FromBase64;
+
+            let bytes = match text.trim().from_base64() {
+                Ok(bytes) => bytes,
+                Err(e) => return Err(LevelError::InvalidField(location.clone(), "data", format!("is not valid base64: {}", e)))
+            };
+
+            let bytes = match compression {
+                None => bytes,
+                Some("zlib") => {
+                    use std::io::Read;
+                    use flate2::read::ZlibDecoder;
+
+                    let mut decoded = Vec::new();
+                    match ZlibDecoder::new(&bytes as &[u8]).read_to_end(&mut decoded) {
+                        Ok(_) => decoded,
+                        Err(e) => return Err(LevelError::InvalidField(location.clone(), "data", format!("is not valid zlib: {}", e)))
+                    }
+                },
+                Some(c) => return Err(LevelError::InvalidField(location.clone(), "compression", format!("'{}' is not supported", c)))
+            };
+
+            if bytes.len() % 4 != 0 {
+                return Err(LevelError::InvalidField(location.clone(), "data", "is not a whole number of tiles".to_string()));
+            }
+
+            // Each global tile ID is a little-endian 32-bit integer
+            Ok(bytes.chunks(4).map(|b| {
+                (b[0] as u32) | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24
+            }).collect())
+        },
+        e => Err(LevelError::InvalidField(location.clone(), "encoding", format!("'{}' is not supported", e)))
+    }
+}
+
+/// Reads an external tileset file (`.tsx`), which holds what a map's `<tileset>` element otherwise would
+fn load_tileset(path: &Path, first_gid: u32) -> Result<(Tileset, Vec<TileData>), LevelError> {
+    use std::fs::File;
+    use std::io::Read;
+
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) => return Err(LevelError::Io(format!("Could not open tileset {}: {}", path.display(), e)))
+    };
+
+    let mut input = String::new();
+    match file.read_to_string(&mut input) {
+        Ok(_) => (),
+        Err(e) => return Err(LevelError::Io(format!("Could not read tileset {}: {}", path.display(), e)))
+    };
+
+    let mut parser = EventReader::new(input.as_bytes());
+    let mut tileset: Option<Tileset> = None;
+    let mut tile_data: Vec<TileData> = Vec::new();
+    let mut in_tile = false;
+
+    for event in parser.events() {
+        match event {
+            XmlEvent::StartElement { name, attributes, .. } => {
+                let element = &name.local_name as &str;
+                if tileset.is_none() {
+                    if element == "tileset" {
+                        tileset = Some(new_tileset(&attributes, first_gid));
+                    }
+                    continue;
+                }
+
+                let tileset = tileset.as_mut().unwrap();
+                match element {
+                    "image" if !in_tile => try!(read_tileset_image(tileset, &attributes)),
+                    "tile" => {
+                        tile_data.push(try!(read_tile(tileset, &attributes)));
+                        in_tile = true;
+                    },
+                    "property" if in_tile => {
+                        let tile = tile_data.last_mut().unwrap();
+                        let loc = Location::Tile { tileset: tile.tileset.clone(), id: tile.id };
+
+                        let (name, value) = try!(read_property(&loc, &attributes));
+                        tile.properties.insert(name, value);
+                    },
+                    _ => ()
+                }
+            },
+            XmlEvent::EndElement { name } => {
+                if name.local_name == "tile" {
+                    in_tile = false;
+                }
+            },
+            XmlEvent::Error(e) => return Err(LevelError::Syntax(format!("{}: {}", path.display(), e))),
+            _ => ()
+        }
+    }
+
+    match tileset {
+        Some(tileset) => Ok((tileset, tile_data)),
+        None => Err(LevelError::Syntax(format!("{}: not a tileset", path.display())))
+    }
+}
+
+/// A tileset from the attributes of a `<tileset>` element, before its image and tiles are read
+fn new_tileset(attributes: &[OwnedAttribute], first_gid: u32) -> Tileset {
+    Tileset {
+        name: get_attribute(attributes, "name").unwrap_or(String::new()),
+        first_gid: first_gid,
+        image: None,
+        image_size: None
+    }
+}
+
+/// Reads a tileset's `<image>` element
+fn read_tileset_image(tileset: &mut Tileset, attributes: &[OwnedAttribute]) -> Result<(), LevelError> {
+    let map = &Location::Map;
+    let width = try!(parse_attribute(map, attributes, "width"));
+    let height = try!(parse_attribute(map, attributes, "height"));
+
+    tileset.image = get_attribute(attributes, "source");
+    tileset.image_size = match (width, height) {
+        (Some(w), Some(h)) => Some((w, h)),
+        _ => None
+    };
+    Ok(())
+}
+
+/// Reads a tileset's `<tile>` element. Its properties come after.
+fn read_tile(tileset: &Tileset, attributes: &[OwnedAttribute]) -> Result<TileData, LevelError> {
+    let id: u32 = try!(require_attribute(&Location::Map, attributes, "id"));
+
+    Ok(TileData {
+        tileset: tileset.name.clone(),
+        id: id,
+        gid: tileset.first_gid + id,
+        properties: Object::new()
+    })
+}
+
+/// Reads a `<property>` element's name and value
+fn read_property(location: &Location, attributes: &[OwnedAttribute]) -> Result<(String, Json), LevelError> {
+    let name: String = try!(require_attribute(location, attributes, "name"));
+    let typ = get_attribute(attributes, "type");
+    let value = get_attribute(attributes, "value").unwrap_or(String::new());
+
+    let value = try!(properties::from_tmx(location, &name, typ.as_ref().map(|t| &t[..]), &value));
+    Ok((name, value))
+}
+
+/// Tiled's `parallaxx` and `parallaxy`, which default to 1
+fn read_parallax(location: &Location, attributes: &[OwnedAttribute]) -> Result<(f32, f32), LevelError> {
+    let x = try!(parse_attribute(location, attributes, "parallaxx")).unwrap_or(1.0);
+    let y = try!(parse_attribute(location, attributes, "parallaxy")).unwrap_or(1.0);
+    Ok((x, y))
+}
+
+/// Layers hidden in Tiled are only there for the level designer
+fn is_visible(attributes: &[OwnedAttribute]) -> bool {
+    get_attribute(attributes, "visible") != Some("0".to_string())
+}
+
+fn get_attribute(attributes: &[OwnedAttribute], key: &str) -> Option<String> {
+    attributes.iter().find(|a| a.name.local_name == key).map(|a| a.value.clone())
+}
+
+fn parse_attribute<T: ::std::str::FromStr>(location: &Location, attributes: &[OwnedAttribute], key: &'static str) -> Result<Option<T>, LevelError> {
+    match get_attribute(attributes, key) {
+        Some(value) => match value.parse() {
+            Ok(v) => Ok(Some(v)),
+            Err(_) => Err(LevelError::InvalidField(location.clone(), key, format!("has an invalid value: {}", value)))
+        },
+        None => Ok(None)
+    }
+}
+
+fn require_attribute<T: ::std::str::FromStr>(location: &Location, attributes: &[OwnedAttribute], key: &'static str) -> Result<T, LevelError> {
+    match try!(parse_attribute(location, attributes, key)) {
+        Some(v) => Ok(v),
+        None => Err(LevelError::MissingField(location.clone(), key))
+    }
+}
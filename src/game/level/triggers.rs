@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use super::{LevelError, Location};
+
+pub type TriggerId = u32;
+
+/// A trigger turning on or off
+#[derive(Copy, Clone, PartialEq)]
+pub enum Signal {
+    On(TriggerId),
+    Off(TriggerId)
+}
+
+/// Names starting with this are reserved for events that come from the game itself
+pub static BUILTIN_PREFIX: &'static str = "game:";
+
+/// Fired when the player reaches the exit ("game:exit")
+pub const TRIGGER_EXIT: TriggerId = 0xFFFF0000;
+
+/// Named triggers are numbered from here. Numbers at or above it can't be used directly.
+const FIRST_NAMED_TRIGGER: TriggerId = 0x80000000;
+
+static BUILTINS: [(&'static str, TriggerId); 1] = [
+    ("game:exit", TRIGGER_EXIT)
+];
+
+/// Resolves the trigger names used in a level to trigger IDs.
+///
+/// A trigger is either a plain number, a built-in event, or a name listed in
+/// the map's `triggers` property.
+#[derive(PartialEq)]
+pub struct TriggerNames {
+    names: HashMap<String, TriggerId>,
+    /// The names from the map's `triggers` property, in order
+    declared: Vec<String>
+}
+
+impl TriggerNames {
+    pub fn new(declared: &[String]) -> Result<TriggerNames, LevelError> {
+        let mut names = HashMap::new();
+
+        for &(name, id) in BUILTINS.iter() {
+            names.insert(name.to_string(), id);
+        }
+
+        for (i, name) in declared.iter().enumerate() {
+            if name.starts_with(BUILTIN_PREFIX) {
+                return Err(LevelError::InvalidTrigger(Location::Map, "triggers".to_string(), name.clone(), "is reserved for the game"));
+            }
+            if name.parse::<TriggerId>().is_ok() {
+                return Err(LevelError::InvalidTrigger(Location::Map, "triggers".to_string(), name.clone(), "is a number, not a name"));
+            }
+            if names.contains_key(name) {
+                return Err(LevelError::InvalidTrigger(Location::Map, "triggers".to_string(), name.clone(), "is declared more than once"));
+            }
+
+            names.insert(name.clone(), FIRST_NAMED_TRIGGER + i as TriggerId);
+        }
+
+        Ok(TriggerNames {
+            names: names,
+            declared: declared.to_vec()
+        })
+    }
+
+    pub fn declared(&self) -> &[String] {
+        &self.declared
+    }
+
+    /// The inverse of `resolve`
+    pub fn name_of(&self, id: TriggerId) -> String {
+        if id < FIRST_NAMED_TRIGGER {
+            return id.to_string();
+        }
+
+        match self.names.iter().find(|&(_, &named_id)| named_id == id) {
+            Some((name, _)) => name.clone(),
+            None => id.to_string()
+        }
+    }
+
+    pub fn resolve(&self, location: &Location, key: &str, name: &str) -> Result<TriggerId, LevelError> {
+        match name.parse::<TriggerId>() {
+            Ok(id) => {
+                if id < FIRST_NAMED_TRIGGER {
+                    Ok(id)
+                } else {
+                    Err(LevelError::InvalidTrigger(location.clone(), key.to_string(), name.to_string(), "is too large"))
+                }
+            },
+            Err(_) => match self.names.get(name) {
+                Some(&id) => Ok(id),
+                None => {
+                    let why = if name.starts_with(BUILTIN_PREFIX) {
+                        "is not a built-in event"
+                    } else {
+                        "is not declared in the map's 'triggers' property"
+                    };
+                    Err(LevelError::InvalidTrigger(location.clone(), key.to_string(), name.to_string(), why))
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::HashSet;
+use super::level::{Level, GateKind, Signal, TriggerId};
+
+// A trigger stays on from the time it fires until it's released.
+// Gates look at which triggers are on, while counters and timers react to a
+// trigger turning on.
+
+#[derive(Clone)]
+struct Gate {
+    kind: GateKind,
+    inputs: Vec<TriggerId>,
+    trigger: TriggerId,
+    was_on: bool
+}
+
+impl Gate {
+    fn is_on(&self, active: &HashSet<TriggerId>) -> bool {
+        match self.kind {
+            GateKind::And => self.inputs.iter().all(|i| active.contains(i)),
+            GateKind::Or => self.inputs.iter().any(|i| active.contains(i)),
+            GateKind::Not => !self.inputs.iter().any(|i| active.contains(i))
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Counter {
+    inputs: Vec<TriggerId>,
+    count: u32,
+    trigger: TriggerId,
+    so_far: u32
+}
+
+#[derive(Clone)]
+struct Timer {
+    inputs: Vec<TriggerId>,
+    delay: u32,
+    trigger: TriggerId,
+    /// Steps left until the timer fires. It fires on the step after this reaches 0.
+    remaining: Option<u32>
+}
+
+#[derive(Clone)]
+pub struct Logic {
+    gates: Vec<Gate>,
+    counters: Vec<Counter>,
+    timers: Vec<Timer>,
+    active: HashSet<TriggerId>
+}
+
+impl Logic {
+    pub fn new(level: &Level, steps_per_second: u32) -> Logic {
+        let gates = level.gates.iter().map(|g| {
+            Gate {
+                kind: g.kind,
+                inputs: g.inputs.clone(),
+                trigger: g.trigger,
+                was_on: false
+            }
+        }).collect();
+
+        let counters = level.counters.iter().map(|c| {
+            Counter {
+                inputs: c.inputs.clone(),
+                count: c.count,
+                trigger: c.trigger,
+                so_far: 0
+            }
+        }).collect();
+
+        let timers = level.timers.iter().map(|t| {
+            Timer {
+                inputs: t.inputs.clone(),
+                delay: (t.delay * steps_per_second as f32) as u32,
+                trigger: t.trigger,
+                remaining: None
+            }
+        }).collect();
+
+        Logic {
+            gates: gates,
+            counters: counters,
+            timers: timers,
+            active: HashSet::new()
+        }
+    }
+
+    /// Takes the signals sent during this step.
+    /// Returns the signals that the logic sends in response.
+    pub fn step(&mut self, signals: &[Signal]) -> Vec<Signal> {
+        let mut output = Vec::new();
+
+        let mut turned_on: Vec<TriggerId> = Vec::new();
+        for signal in signals.iter() {
+            match *signal {
+                Signal::On(id) => {
+                    if self.active.insert(id) {
+                        turned_on.push(id);
+                    }
+                },
+                Signal::Off(id) => {
+                    self.active.remove(&id);
+                }
+            }
+        }
+
+        for gate in self.gates.iter_mut() {
+            let is_on = gate.is_on(&self.active);
+            match (gate.was_on, is_on) {
+                (false, true) => output.push(Signal::On(gate.trigger)),
+                (true, false) => output.push(Signal::Off(gate.trigger)),
+                _ => ()
+            }
+            gate.was_on = is_on;
+        }
+
+        for counter in self.counters.iter_mut().filter(|c| c.so_far < c.count) {
+            let hits = turned_on.iter().filter(|id| counter.inputs.contains(*id)).count();
+            counter.so_far += hits as u32;
+            if counter.so_far >= counter.count {
+                output.push(Signal::On(counter.trigger));
+            }
+        }
+
+        for timer in self.timers.iter_mut() {
+            timer.remaining = match timer.remaining {
+                Some(0) => {
+                    output.push(Signal::On(timer.trigger));
+                    None
+                },
+                Some(steps) => Some(steps - 1),
+                None => {
+                    if !turned_on.iter().any(|id| timer.inputs.contains(id)) {
+                        None
+                    } else if timer.delay == 0 {
+                        output.push(Signal::On(timer.trigger));
+                        None
+                    } else {
+                        // This step counts as the first one of the delay
+                        Some(timer.delay - 1)
+                    }
+                }
+            };
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+    use super::{Logic, Gate, Counter, Timer};
+    use super::super::level::{GateKind, Signal};
+    use super::super::level::Signal::{On, Off};
+
+    fn logic(gates: Vec<Gate>, counters: Vec<Counter>, timers: Vec<Timer>) -> Logic {
+        Logic {
+            gates: gates,
+            counters: counters,
+            timers: timers,
+            active: HashSet::new()
+        }
+    }
+
+    fn timer(delay: u32) -> Logic {
+        logic(vec![], vec![], vec![Timer { inputs: vec![1], delay: delay, trigger: 9, remaining: None }])
+    }
+
+    /// How many steps after its input turns on the logic first sends `expected`
+    fn steps_until(logic: &mut Logic, input: Signal, expected: Signal) -> Option<u32> {
+        if logic.step(&[input]).contains(&expected) {
+            return Some(0);
+        }
+        (1..100).find(|_| logic.step(&[]).contains(&expected))
+    }
+
+    #[test]
+    fn gate_follows_its_inputs() {
+        let mut logic = logic(vec![Gate { kind: GateKind::And, inputs: vec![1, 2], trigger: 9, was_on: false }], vec![], vec![]);
+
+        assert!(logic.step(&[On(1)]).is_empty());
+        // Turns on in the same step as its last input
+        assert!(logic.step(&[On(2)]) == vec![On(9)]);
+        assert!(logic.step(&[]).is_empty());
+        assert!(logic.step(&[Off(1)]) == vec![Off(9)]);
+    }
+
+    #[test]
+    fn counter_fires_on_the_last_hit() {
+        let mut logic = logic(vec![], vec![Counter { inputs: vec![1], count: 2, trigger: 9, so_far: 0 }], vec![]);
+
+        assert!(logic.step(&[On(1)]).is_empty());
+        assert!(logic.step(&[Off(1)]).is_empty());
+        assert!(logic.step(&[On(1)]) == vec![On(9)]);
+        // Only fires once
+        assert!(logic.step(&[Off(1)]).is_empty());
+        assert!(logic.step(&[On(1)]).is_empty());
+    }
+
+    #[test]
+    fn timer_fires_after_its_delay() {
+        assert!(steps_until(&mut timer(0), On(1), On(9)) == Some(0));
+        assert!(steps_until(&mut timer(1), On(1), On(9)) == Some(1));
+        assert!(steps_until(&mut timer(60), On(1), On(9)) == Some(60));
+    }
+
+    #[test]
+    fn timer_starts_again_when_its_input_turns_on_again() {
+        let mut logic = timer(2);
+
+        assert!(steps_until(&mut logic, On(1), On(9)) == Some(2));
+        // The input is still on, so it hasn't turned on again
+        assert!(steps_until(&mut logic, On(1), On(9)) == None);
+
+        logic.step(&[Off(1)]);
+        assert!(steps_until(&mut logic, On(1), On(9)) == Some(2));
+    }
+}
@@ -4,17 +4,30 @@ use cgmath;
 use game_platforms::{PlatformStepResult, GameStepper};
 use game_platforms::sdl2_opengl::Input;
 use self::audio::Audio;
-use self::items::DynamicItems;
-use self::level::Level;
+use self::campaign::Campaign;
+use self::checkpoint::ReachedCheckpoint;
+use self::inventory::{Ability, ItemUse, UseContext};
+use self::items::{DynamicItems, ItemEvent};
+use self::level::{Level, ObjectTypes, Signal, TileKind, TRIGGER_EXIT};
+use self::logic::Logic;
+use self::physics::PlayerPhysics;
 use self::player::Player;
 use self::rect::Point;
+use self::watcher::LevelWatcher;
 
 mod audio;
+pub mod campaign;
+mod checkpoint;
 mod collision;
+mod grid;
+pub mod inventory;
 mod items;
-mod level;
+pub mod level;
+mod logic;
+pub mod physics;
 mod rect;
 mod player;
+mod watcher;
 mod wrapping;
 pub mod render;
 
@@ -22,20 +35,47 @@ pub struct Game<'sdl> {
     audio: Option<Audio<'sdl>>,
     pub level: Level,
     pub items: DynamicItems,
+    logic: Logic,
     player: Player,
+    /// Where the player respawns, if they've reached a checkpoint in this level
+    checkpoint: Option<ReachedCheckpoint>,
+    /// The physics the game was started with, before the level changes any
+    base_physics: PlayerPhysics,
+    /// What the player moves by in the current level
+    physics: PlayerPhysics,
     scroll_x: f32,
     scroll_y: f32,
-    exited: bool
+    exited: bool,
+    campaign: Campaign,
+    /// Counts down to the next level after the player reaches the exit
+    level_transition: Option<u32>,
+    campaign_complete: bool,
+    /// None of the campaign's remaining levels could be loaded. The game stops.
+    campaign_failed: bool,
+    /// The player is out of lives. Waits for them to restart.
+    game_over: bool,
+    /// Reloads the current level when its file changes
+    watcher: LevelWatcher,
+    object_types: ObjectTypes
 }
 
+static STEPS_PER_SECOND: u32 = 60;
+
+/// How long to wait after reaching the exit before the next level starts
+static LEVEL_TRANSITION_STEPS: u32 = 120;
+
+/// How fast the backdrop scrolls in levels that don't have parallax layers
+static DEFAULT_PARALLAX_RATE: f32 = 0.5;
+
 pub struct GameStepResult {
     viewport: (i32, i32),
     projection_view: cgmath::Matrix4<f32>,
-    projection_view_parallax: cgmath::Matrix4<f32>,
+    /// One for each of the level's parallax layers
+    projection_view_parallax: Vec<cgmath::Matrix4<f32>>,
 }
 
 impl<'sdl> Game<'sdl> {
-    pub fn new<'a>(sdl: &'a sdl2::Sdl) -> Game<'a> {
+    pub fn new<'a>(sdl: &'a sdl2::Sdl, campaign: Campaign, object_types: ObjectTypes, physics: PlayerPhysics) -> Game<'a> {
         let audio = match Audio::new(sdl) {
             Ok(audio) => Some(audio),
             Err(e) => {
@@ -44,20 +84,179 @@ impl<'sdl> Game<'sdl> {
                 None
             }
         };
-        let level = Level::load();
-        let items = DynamicItems::new(&level);
+        let level = match Level::load_from_path(campaign.current_level(), &object_types) {
+            Ok(level) => level,
+            Err(e) => {
+                println!("{}", e);
+                println!("Using the built-in level");
+                Level::load()
+            }
+        };
+        let items = DynamicItems::new(&level, STEPS_PER_SECOND);
+        let logic = Logic::new(&level, STEPS_PER_SECOND);
         let player = Player::new(Point::new(&level.get_screen(), level.player_start_pos));
+        let level_physics = physics.with(&level.physics);
         let scroll_x = 0.0;
+        let watcher = LevelWatcher::new(campaign.current_level(), STEPS_PER_SECOND);
 
         Game {
             audio: audio,
             level: level,
             items: items,
+            logic: logic,
             player: player,
+            checkpoint: None,
+            base_physics: physics,
+            physics: level_physics,
             scroll_x: scroll_x,
             scroll_y: 0.0,
-            exited: false
+            exited: false,
+            campaign: campaign,
+            level_transition: None,
+            campaign_complete: false,
+            campaign_failed: false,
+            game_over: false,
+            watcher: watcher,
+            object_types: object_types
+        }
+    }
+
+    pub fn is_campaign_complete(&self) -> bool { self.campaign_complete }
+
+    pub fn is_campaign_failed(&self) -> bool { self.campaign_failed }
+
+    pub fn is_game_over(&self) -> bool { self.game_over }
+
+    fn start_level(&mut self, level: Level) {
+        let pos = Point::new(&level.get_screen(), level.player_start_pos);
+
+        if level.keep_inventory {
+            self.player.enter_level(pos);
+        } else {
+            // Lives aren't part of the inventory
+            let lives = self.player.lives;
+            self.player = Player::new(pos);
+            self.player.lives = lives;
+        }
+
+        self.items = DynamicItems::new(&level, STEPS_PER_SECOND);
+        self.logic = Logic::new(&level, STEPS_PER_SECOND);
+        self.checkpoint = None;
+        self.physics = self.base_physics.with(&level.physics);
+        self.level = level;
+        self.scroll_x = 0.0;
+        self.scroll_y = 0.0;
+        self.exited = false;
+        self.level_transition = None;
+        self.watcher = LevelWatcher::new(self.campaign.current_level(), STEPS_PER_SECOND);
+    }
+
+    /// Swaps in a new version of the current level.
+    /// The player keeps their position and items, unless they'd end up outside the level.
+    fn reload_level(&mut self, level: Level) {
+        let screen = level.get_screen();
+        let (x, y) = self.player.get_pos().xy();
+        let (width, height) = screen.size();
+
+        if x >= width || y >= height {
+            self.player.enter_level(Point::new(&screen, level.player_start_pos));
+        }
+
+        let (scroll_x, scroll_y) = screen.wrap_coord((self.scroll_x, self.scroll_y));
+
+        self.items = DynamicItems::new(&level, STEPS_PER_SECOND);
+        self.logic = Logic::new(&level, STEPS_PER_SECOND);
+        self.checkpoint = None;
+        self.physics = self.base_physics.with(&level.physics);
+        self.level = level;
+        self.scroll_x = scroll_x;
+        self.scroll_y = scroll_y;
+    }
+
+    /// Loads the next level in the campaign. Levels that fail to load are skipped.
+    /// Returns false if there's no level left that could be loaded.
+    fn next_level(&mut self) -> bool {
+        while self.campaign.advance() {
+            match Level::load_from_path(self.campaign.current_level(), &self.object_types) {
+                Ok(level) => {
+                    self.start_level(level);
+                    return true;
+                },
+                Err(e) => {
+                    println!("{}", e);
+                    println!("Skipping level {}", self.campaign.current_index() + 1);
+                }
+            }
         }
+
+        false
+    }
+
+    /// Starts the campaign over from its first level, with a new player.
+    /// If none of the campaign's levels can be loaded any more, the campaign fails.
+    fn restart(&mut self) {
+        self.campaign.restart();
+
+        let started = match Level::load_from_path(self.campaign.current_level(), &self.object_types) {
+            Ok(level) => {
+                self.start_level(level);
+                true
+            },
+            Err(e) => {
+                println!("{}", e);
+                println!("Skipping level 1");
+                self.next_level()
+            }
+        };
+        if !started {
+            println!("None of the campaign's levels could be loaded");
+            self.campaign_failed = true;
+            return;
+        }
+
+        self.player = Player::new(Point::new(&self.level.get_screen(), self.level.player_start_pos));
+        self.game_over = false;
+        self.campaign_complete = false;
+    }
+
+    /// Sends a signal to both the items and the level.
+    /// Returns true if anything appeared or disappeared.
+    fn signal(&mut self, signal: Signal) -> bool {
+        let items_changed = self.items.signal(signal);
+        let level_changed = self.level.signal_set_to(signal);
+        items_changed || level_changed
+    }
+
+    /// Does what the items asked for since this was last called.
+    /// Returns where the player respawns from now on, if they reached a checkpoint.
+    fn handle_item_events(&mut self) -> Option<Point<f32>> {
+        let mut checkpoint = None;
+        let mut play_poof_sound = false;
+
+        for event in self.items.take_events().into_iter() {
+            match event {
+                ItemEvent::Signal(signal) => play_poof_sound |= self.signal(signal),
+                ItemEvent::Give(name, count) => {
+                    if let Some(id) = self.object_types.items.find(&name) {
+                        self.player.inventory.add(&self.object_types.items, id, count);
+                    }
+                },
+                ItemEvent::Checkpoint(xy) => checkpoint = Some(xy),
+                ItemEvent::Sound(sound) => {
+                    if let Some(ref mut audio) = self.audio {
+                        audio.play(sound);
+                    }
+                }
+            }
+        }
+
+        if play_poof_sound {
+            if let Some(ref mut audio) = self.audio {
+                audio.poof();
+            }
+        }
+
+        checkpoint
     }
 
     fn scroll(&mut self, x: f32, y: f32) {
@@ -72,7 +271,7 @@ impl<'sdl> Game<'sdl> {
 impl<'sdl> GameStepper<Input> for Game<'sdl> {
     type StepResult = GameStepResult;
 
-    fn steps_per_second(&self) -> u32 { 60 }
+    fn steps_per_second(&self) -> u32 { STEPS_PER_SECOND }
     fn step(&mut self, input: &Input) -> PlatformStepResult<GameStepResult> {
         use game_platforms::PlatformStepResult::{Continue, Exit};
         use sdl2::keycode::KeyCode;
@@ -81,6 +280,38 @@ impl<'sdl> GameStepper<Input> for Game<'sdl> {
             return Exit;
         }
 
+        if self.game_over && (input.is_keycode_newly_down(KeyCode::Return) | input.is_keycode_newly_down(KeyCode::R)) {
+            self.restart();
+        }
+
+        if let Some(steps) = self.level_transition {
+            if steps == 0 {
+                self.level_transition = None;
+                if !self.next_level() {
+                    println!("None of the remaining levels could be loaded");
+                    self.campaign_failed = true;
+                }
+            } else {
+                self.level_transition = Some(steps - 1);
+            }
+        }
+
+        if self.campaign_failed {
+            return Exit;
+        }
+
+        match self.watcher.step(&self.object_types) {
+            Some(Ok(level)) => {
+                println!("Reloaded {}", self.watcher.path().display());
+                self.reload_level(level);
+            },
+            Some(Err(e)) => {
+                println!("{}", e);
+                println!("Keeping the previous version of the level");
+            },
+            None => ()
+        }
+
         let screen = self.level.get_screen();
 
         let lock_scrolling = input.is_keycode_down(KeyCode::LCtrl) | input.is_keycode_down(KeyCode::RCtrl) | input.is_keycode_down(KeyCode::ScrollLock) | !self.player.is_alive();
@@ -90,74 +321,71 @@ impl<'sdl> GameStepper<Input> for Game<'sdl> {
         let new_down = input.is_keycode_newly_down(KeyCode::Down) | input.is_keycode_newly_down(KeyCode::S);
         let left = input.is_keycode_down(KeyCode::Left) | input.is_keycode_down(KeyCode::A);
         let right = input.is_keycode_down(KeyCode::Right) | input.is_keycode_down(KeyCode::D);
+        let jump = input.is_keycode_down(KeyCode::Z) | input.is_keycode_down(KeyCode::K);
+        let new_jump = input.is_keycode_newly_down(KeyCode::Z) | input.is_keycode_newly_down(KeyCode::K);
+        let switch_tool = input.is_keycode_newly_down(KeyCode::Q) | input.is_keycode_newly_down(KeyCode::Tab);
+
+        if switch_tool {
+            self.player.inventory.select_next(&self.object_types.items);
+        }
 
         let last_player_pos = self.player.get_pos();
         let last_player_is_walking = self.player.is_walking();
         let last_player_is_drilling = self.player.is_drilling();
         let last_player_is_jumping = self.player.is_jumping();
 
-        if up {
+        if up || down {
             let player_rect = self.player.get_rect();
-            let beanstalk = self.items.beanstalk_exists(&player_rect);
+            // Beanstalks only go up. Ladders can also be climbed down from the top.
+            let beanstalk = if up { self.items.climbable_at(&player_rect) } else { None };
+            let ladder_rect = if down { player_rect.offset(&screen, 0.0, 1.0) } else { player_rect };
+            let climbable = beanstalk.or_else(|| self.level.get_tiles().ladder_at(&ladder_rect));
 
-            match beanstalk {
+            match climbable {
                 Some(rect) => {
-                    self.player.try_climb_beanstalk(&screen, &rect);
+                    self.player.try_climb(&screen, &rect);
                 },
                 None => ()
             }
         }
 
-        let died = if self.player.is_alive() && self.items.rect_hits_monsters(&self.player.get_rect()) {
-            self.items.add_poof(last_player_pos);
-            true
+        let hurt = if self.items.hurts(&self.player.get_rect()) {
+            let respawn = match self.checkpoint {
+                Some(ref checkpoint) => checkpoint.respawn,
+                None => Point::new(&screen, self.level.player_start_pos)
+            };
+            self.player.hurt(respawn)
         } else {
             false
         };
+        let died = hurt && !self.player.is_alive();
 
         if died {
-            self.player.die(Point::new(&screen, self.level.player_start_pos));
+            self.items.add_poof(last_player_pos);
         }
 
-        self.player.tick(&screen, self.level.get_tiles(), up, down, left, right);
+        let was_alive = self.player.is_alive();
+        let can_dig = self.player.inventory.has_ability(&self.object_types.items, Ability::Dig);
+        self.player.tick(&screen, self.level.get_tiles(), &self.physics, up, down, left, right, jump, new_jump, can_dig);
+
+        if !was_alive && self.player.is_alive() {
+            if let Some(ref checkpoint) = self.checkpoint {
+                checkpoint.restore(&mut self.level, &mut self.items, &mut self.logic, &mut self.player, &self.object_types.items);
+            }
+        }
         let cur_player_pos = self.player.get_pos();
         let cur_player_is_walking = self.player.is_walking();
         let cur_player_is_drilling = self.player.is_drilling();
         let cur_player_is_jumping = self.player.is_jumping();
         let cur_player_rect = self.player.get_rect();
 
-        let mut got_useless_points = false;
-
-        let got_item = if new_down {
-            let items = self.items.try_open_chest(&cur_player_rect);
-
-            for item in items.iter() {
-                use self::items::ChestItem;
-
-                match item {
-                    &(_, _, ChestItem::Drill) => {
-                        self.player.add_drill();
-                    },
-                    &(_, _, ChestItem::Gun) => {
-                        self.player.add_gun();
-                    },
-                    &(x, y, ChestItem::UselessPoints) => {
-                        self.items.add_useless_points(Point::new(&screen, (x, y)));
-                        got_useless_points = true;
-                    },
-                    &(_, _, ChestItem::None) => ()
-                }
-
-                self.items.add_poof(cur_player_pos.offset(&screen, 5.0, 5.0));
-            }
-
-            items.len() > 0
-        } else {
-            false
-        };
+        if !self.game_over && self.player.is_game_over() {
+            self.game_over = true;
+            println!("Game over! Press Enter to start again");
+        }
 
         let used_key = if let Some((x, y)) = self.level.get_tiles().is_key_entrance_beside(&cur_player_rect) {
-            if self.player.try_use_key() {
+            if self.player.inventory.use_up(&self.object_types.items, Ability::Unlock) {
                 self.level.get_tiles_mut().remove_key_entrance(x, y);
                 true
             } else {
@@ -167,16 +395,8 @@ impl<'sdl> GameStepper<Input> for Game<'sdl> {
             false
         };
 
-        let got_key = match self.items.try_take_keys(&cur_player_rect) {
-            0 => false,
-            amount => {
-                self.player.add_keys(amount);
-                true
-            }
-        };
-
         let just_exited = if new_down {
-            if let Some((_x, _y)) = self.level.get_tiles().is_tile_inside(&cur_player_rect, 0x2B) {
+            if let Some((_x, _y)) = self.level.get_tiles().is_kind_inside(&cur_player_rect, TileKind::Exit) {
                 true
             } else {
                 false
@@ -190,49 +410,50 @@ impl<'sdl> GameStepper<Input> for Game<'sdl> {
             self.level.get_tiles_mut().take_coins(&rect)
         };
 
-        if just_exited {
+        if just_exited && !self.exited {
             self.exited = true;
-            self.items.trigger(255);
-            self.level.trigger_set_to(255);
+            self.signal(Signal::On(TRIGGER_EXIT));
+
+            if self.campaign.is_last_level() {
+                self.campaign_complete = true;
+            } else {
+                self.level_transition = Some(LEVEL_TRANSITION_STEPS);
+            }
         }
 
+        // Chests, keys, switches and checkpoints
+        self.items.touch(&cur_player_rect, new_down, self.player.is_alive());
+        let reached_checkpoint = self.handle_item_events();
+
         self.items.step(&screen);
         self.items.bullet_item_collision(self.level.get_tiles());
 
-        let gun_fired = if fire {
-            if let self::player::PlayerState::Stand(ref s) = self.player.state {
-                if let Some(_) = self.player.gun {
-                    use self::player::PlayerStandDirection::{Left, Right};
-
-                    let (bullet_coord, vel_x) = match s.direction {
-                        Left => (cur_player_pos.offset(&screen, -4.0, 12.0), -8.0),
-                        Right => (cur_player_pos.offset(&screen, 20.0, 12.0), 8.0)
-                    };
-                    self.items.add_bullet(bullet_coord, vel_x);
-                    true
-                } else {
-                    false
+        // Fire uses the selected item, whatever it does
+        if fire {
+            if let Some(id) = self.player.inventory.selected() {
+                if let ItemUse::Active(action) = self.object_types.items.get(id).usage {
+                    action(&mut UseContext {
+                        screen: &screen,
+                        player: &self.player,
+                        items: &mut self.items,
+                        audio: self.audio.as_mut()
+                    });
                 }
-            } else {
-                false
             }
-        } else {
-            false
-        };
+        }
 
         {
-            let switch_triggers: Vec<u8> = {
-                let switches = self.items.switch_hit_test(&cur_player_rect);
-
-                switches.iter().map(|switch| {
-                    switch.trigger
-                }).collect()
-            };
             let mut play_poof_sound = false;
 
-            for trigger in switch_triggers.iter() {
-                play_poof_sound |= self.items.trigger(*trigger);
-                play_poof_sound |= self.level.trigger_set_to(*trigger);
+            // Logic gates, counters and timers respond to every signal sent
+            let signals = self.items.take_signals();
+            for signal in self.logic.step(&signals).iter() {
+                play_poof_sound |= self.signal(*signal);
+            }
+
+            // Saved once everything the checkpoint set off has happened
+            if let Some(respawn) = reached_checkpoint {
+                self.checkpoint = Some(ReachedCheckpoint::new(respawn, &self.level, &self.items, &self.logic, &self.player, &self.object_types.items));
             }
 
             let (_moved, destroyed) = if !lock_scrolling {
@@ -253,6 +474,9 @@ impl<'sdl> GameStepper<Input> for Game<'sdl> {
                 (false, false)
             };
 
+            // Crushed chests can set off their explode triggers
+            self.handle_item_events();
+
             if let Some(ref mut audio) = self.audio {
                 match (last_player_is_walking, cur_player_is_walking) {
                     (false, true) => audio.start_walking(),
@@ -275,43 +499,43 @@ impl<'sdl> GameStepper<Input> for Game<'sdl> {
 
                 if play_poof_sound { audio.poof(); }
 
-                if got_item { audio.item_get(); }
-
-                if got_useless_points { audio.nothing(); }
-
-                if got_key { audio.key_get(); }
-
                 if used_key { audio.unlock(); }
 
-                if gun_fired { audio.fire(); }
-
                 if new_coins > 0 {
                     audio.coin();
                 }
 
-                if died { audio.die(); }
+                if died { audio.die(); } else if hurt { audio.hurt(); }
             }
         }
 
+        let (screen_width, screen_height) = screen.size();
+
         let projection_view = cgmath::ortho(
             0.0 + self.scroll_x,
-            self.level.width as f32 * 16.0 + self.scroll_x,
-            self.level.height as f32 * 16.0 + self.scroll_y,
+            screen_width + self.scroll_x,
+            screen_height + self.scroll_y,
             0.0 + self.scroll_y,
             -1.0,
             1.0
         );
 
-        let parallax_rate = 0.5;
+        let parallax_rates: Vec<(f32, f32)> = if self.level.parallax_layers.is_empty() {
+            vec![(DEFAULT_PARALLAX_RATE, DEFAULT_PARALLAX_RATE)]
+        } else {
+            self.level.parallax_layers.iter().map(|layer| layer.rate).collect()
+        };
 
-        let projection_view_parallax = cgmath::ortho(
-            0.0 + self.scroll_x * parallax_rate,
-            self.level.width as f32 * 16.0 + self.scroll_x * parallax_rate,
-            self.level.height as f32 * 16.0 + self.scroll_y * parallax_rate,
-            0.0 + self.scroll_y * parallax_rate,
-            -1.0,
-            1.0
-        );
+        let projection_view_parallax = parallax_rates.iter().map(|&(rate_x, rate_y)| {
+            cgmath::ortho(
+                0.0 + self.scroll_x * rate_x,
+                screen_width + self.scroll_x * rate_x,
+                screen_height + self.scroll_y * rate_y,
+                0.0 + self.scroll_y * rate_y,
+                -1.0,
+                1.0
+            )
+        }).collect();
 
         Continue(GameStepResult {
             viewport: input.get_viewport(),
@@ -0,0 +1,203 @@
+use std::path::Path;
+
+/// How the player moves. Speeds are in pixels per step, and accelerations in pixels per step per step.
+///
+/// The game's own values can be replaced with `--physics <file>`,
+/// and each level can change some of them with `physics.<setting>` map properties.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct PlayerPhysics {
+    pub gravity: f32,
+    /// The fastest the player can fall
+    pub terminal_velocity: f32,
+    /// How quickly the player speeds up while running
+    pub acceleration: f32,
+    /// What the player's speed is multiplied by each step they're not running
+    pub friction: f32,
+    pub max_speed: f32,
+    pub dig_speed: f32,
+    pub climb_speed: f32,
+    /// How high the player's arc is when jumping off a beanstalk or out of the dirt
+    pub emerge_arc: f32,
+    /// How fast a jump starts, upwards
+    pub jump_speed: f32,
+    /// Gravity while rising from a jump with the jump button held, so holding it jumps higher
+    pub jump_hold_gravity: f32,
+    /// How many steps after walking off a ledge the player can still jump
+    pub coyote_steps: u32,
+    /// How many steps a jump pressed in the air is remembered for, in case the player lands
+    pub jump_buffer_steps: u32
+}
+
+/// Why a setting couldn't be changed
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum SettingError {
+    /// There's no setting with that name
+    Unknown,
+    /// The value doesn't fit. Describes what it should be.
+    Invalid(&'static str)
+}
+
+impl PlayerPhysics {
+    pub fn new() -> PlayerPhysics {
+        PlayerPhysics {
+            gravity: 0.5,
+            terminal_velocity: 10.0,
+            acceleration: 0.25,
+            friction: 0.8,
+            max_speed: 3.0,
+            dig_speed: 2.0,
+            climb_speed: 2.0,
+            emerge_arc: 2.3,
+            jump_speed: 5.0,
+            jump_hold_gravity: 0.25,
+            coyote_steps: 6,
+            jump_buffer_steps: 6
+        }
+    }
+
+    /// Changes a setting by the name used in physics files and level properties
+    pub fn set(&mut self, name: &str, value: f32) -> Result<(), SettingError> {
+        use std::num::Float;
+
+        if !(value >= 0.0) || Float::is_infinite(value) {
+            return Err(SettingError::Invalid("a number of at least 0"));
+        }
+        let steps = || if Float::fract(value) == 0.0 { Ok(value as u32) } else { Err(SettingError::Invalid("a whole number")) };
+
+        match name {
+            "gravity" => self.gravity = value,
+            "terminal_velocity" => self.terminal_velocity = value,
+            "acceleration" => self.acceleration = value,
+            "friction" => {
+                if value > 1.0 {
+                    return Err(SettingError::Invalid("a number from 0 to 1"));
+                }
+                self.friction = value;
+            },
+            "max_speed" => self.max_speed = value,
+            "dig_speed" => self.dig_speed = value,
+            "climb_speed" => self.climb_speed = value,
+            "emerge_arc" => {
+                // The arc is a sine curve, which stops making sense once it passes its peak
+                if value == 0.0 || value >= 3.0 {
+                    return Err(SettingError::Invalid("a number between 0 and 3"));
+                }
+                self.emerge_arc = value;
+            },
+            "jump_speed" => self.jump_speed = value,
+            "jump_hold_gravity" => self.jump_hold_gravity = value,
+            "coyote_steps" => self.coyote_steps = try!(steps()),
+            "jump_buffer_steps" => self.jump_buffer_steps = try!(steps()),
+            _ => return Err(SettingError::Unknown)
+        }
+
+        Ok(())
+    }
+
+    /// A copy with some settings changed. The settings must already have been checked by `set`.
+    pub fn with(&self, settings: &[(String, f32)]) -> PlayerPhysics {
+        let mut physics = *self;
+        for &(ref name, value) in settings.iter() {
+            let _ = physics.set(name, value);
+        }
+        physics
+    }
+
+    /// Loads a physics file.
+    ///
+    /// Each line is a setting, as `name = value`. Settings that aren't there keep the game's values.
+    /// Blank lines and lines starting with `#` are ignored.
+    pub fn load_from_path<P: AsRef<Path>>(path: P) -> Result<PlayerPhysics, String> {
+        use std::fs::File;
+        use std::io::Read;
+
+        let path = path.as_ref();
+
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) => return Err(format!("Could not open {}: {}", path.display(), e))
+        };
+
+        let mut data = String::new();
+        match file.read_to_string(&mut data) {
+            Ok(_) => (),
+            Err(e) => return Err(format!("Could not read {}: {}", path.display(), e))
+        };
+
+        match PlayerPhysics::parse(&data) {
+            Ok(physics) => Ok(physics),
+            Err(e) => Err(format!("{}: {}", path.display(), e))
+        }
+    }
+
+    fn parse(data: &str) -> Result<PlayerPhysics, String> {
+        let mut physics = PlayerPhysics::new();
+
+        for (index, line) in data.lines().map(|line| line.trim()).enumerate() {
+            if line.is_empty() || line.starts_with("#") {
+                continue;
+            }
+
+            let mut parts = line.splitn(2, '=').map(|part| part.trim());
+            let (name, value) = match (parts.next(), parts.next()) {
+                (Some(name), Some(value)) => (name, value),
+                _ => return Err(format!("line {}: expected 'name = value'", index + 1))
+            };
+
+            let value = match value.parse() {
+                Ok(value) => value,
+                Err(_) => return Err(format!("line {}: '{}' is not a number", index + 1, value))
+            };
+
+            match physics.set(name, value) {
+                Ok(()) => (),
+                Err(SettingError::Unknown) => return Err(format!("line {}: unknown setting '{}'", index + 1, name)),
+                Err(SettingError::Invalid(expected)) => return Err(format!("line {}: '{}' should be {}", index + 1, name, expected))
+            }
+        }
+
+        Ok(physics)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PlayerPhysics, SettingError};
+
+    #[test]
+    fn parse() {
+        let physics = PlayerPhysics::parse("# Floaty\ngravity = 0.25\n\n  max_speed=4\ncoyote_steps = 10\n").unwrap();
+
+        assert!(physics.gravity == 0.25);
+        assert!(physics.max_speed == 4.0);
+        assert!(physics.coyote_steps == 10);
+        assert!(physics.friction == PlayerPhysics::new().friction);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(PlayerPhysics::parse("gravity").is_err());
+        assert!(PlayerPhysics::parse("gravity = lots").is_err());
+        assert!(PlayerPhysics::parse("gravity = -1").is_err());
+        assert!(PlayerPhysics::parse("levity = 1").is_err());
+        assert!(PlayerPhysics::parse("coyote_steps = 2.5").is_err());
+    }
+
+    #[test]
+    fn set() {
+        let mut physics = PlayerPhysics::new();
+
+        assert!(physics.set("friction", 0.5) == Ok(()));
+        assert!(physics.friction == 0.5);
+        assert!(physics.set("friction", 1.5) == Err(SettingError::Invalid("a number from 0 to 1")));
+        assert!(physics.set("levity", 1.0) == Err(SettingError::Unknown));
+    }
+
+    #[test]
+    fn with() {
+        let physics = PlayerPhysics::new().with(&[("jump_speed".to_string(), 7.0)]);
+
+        assert!(physics.jump_speed == 7.0);
+        assert!(physics.gravity == PlayerPhysics::new().gravity);
+    }
+}
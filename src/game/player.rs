@@ -1,4 +1,6 @@
+use super::inventory::Inventory;
 use super::level::Tiles;
+use super::physics::PlayerPhysics;
 use super::wrapping::Screen;
 use super::rect::{Point, Rect};
 
@@ -20,12 +22,6 @@ fn into_direction(up: bool, down: bool, left: bool, right: bool) -> (Option<bool
     (direction_right, direction_down)
 }
 
-pub struct PlayerItemDrill {
-    pub phase: f32
-}
-
-pub struct PlayerItemGun;
-
 #[derive(Copy, Clone)]
 pub enum PlayerStandDirection {
     Left,
@@ -55,27 +51,78 @@ pub struct PlayerStateStand {
     pub running_cycle: Option<f32>,
     vel_x: f32,
     vel_y: f32,
+    /// Steps since the player was last on the ground
+    air_steps: u32,
+    /// Whether the player has jumped since they were last on the ground
+    jumped: bool,
+    /// Steps since jump was pressed, if it hasn't been used yet
+    jump_buffer: Option<u32>
 }
 
 impl PlayerStateStand {
-    fn apply_gravity(&mut self, screen: &Screen, tiles: &Tiles) {
+    fn new(direction: PlayerStandDirection, xy: Point<f32>) -> PlayerStateStand {
+        PlayerStateStand {
+            direction: direction,
+            xy: xy,
+            running_cycle: None,
+            vel_x: 0.0,
+            vel_y: 0.0,
+            air_steps: 0,
+            jumped: false,
+            jump_buffer: None
+        }
+    }
+
+    fn jump(&mut self, screen: &Screen, tiles: &Tiles, physics: &PlayerPhysics, pressed: bool) {
+        let on_ground = self.vel_y >= 0.0 && self.ground_below(screen, tiles, 1.0).is_some();
+        if on_ground {
+            self.air_steps = 0;
+            self.jumped = false;
+        } else if self.air_steps <= physics.coyote_steps {
+            self.air_steps += 1;
+        }
+
+        if pressed {
+            self.jump_buffer = Some(0);
+        }
+
+        self.jump_buffer = match self.jump_buffer {
+            Some(_) if !self.jumped && self.air_steps <= physics.coyote_steps => {
+                self.vel_y = -physics.jump_speed;
+                self.jumped = true;
+                None
+            },
+            Some(steps) if steps < physics.jump_buffer_steps => Some(steps + 1),
+            _ => None
+        };
+    }
+
+    fn is_rising_from_jump(&self) -> bool {
+        self.jumped && self.vel_y < 0.0
+    }
+
+    fn apply_gravity(&mut self, screen: &Screen, tiles: &Tiles, physics: &PlayerPhysics, jump_held: bool) {
+        let gravity = if jump_held && self.is_rising_from_jump() { physics.jump_hold_gravity } else { physics.gravity };
+
         let vel_y = {
             let vy = self.vel_y;
             if self.go(screen, tiles, 0.0, vy) {
                 0.0
             } else {
-                vy + 0.5
+                vy + gravity
             }
         };
-        self.vel_y = if vel_y > 10.0 { 10.0 }
+        self.vel_y = if vel_y > physics.terminal_velocity { physics.terminal_velocity }
         else { vel_y };
     }
 
-    fn run(&mut self, screen: &Screen, tiles: &Tiles, left: bool, right: bool) {
+    fn run(&mut self, screen: &Screen, tiles: &Tiles, physics: &PlayerPhysics, left: bool, right: bool) {
         use std::num::Float;
 
-        let speed_increment = 0.25;
-        let speed_slowdown = 0.8;
+        let was_on_ground = self.vel_y >= 0.0 && self.ground_below(screen, tiles, 1.0).is_some();
+
+        let speed_increment = physics.acceleration;
+        let speed_slowdown = physics.friction;
 
         let vel_x = {
             let vx = if left {
@@ -86,7 +133,7 @@ impl PlayerStateStand {
                 self.vel_x * speed_slowdown
             };
 
-            let max_speed = 3.0;
+            let max_speed = physics.max_speed;
 
             if vx > max_speed { max_speed }
             else if vx < -max_speed { -max_speed }
@@ -95,6 +142,13 @@ impl PlayerStateStand {
 
         self.go(screen, tiles, vel_x, 0.0);
 
+        // Walk down slopes instead of bouncing down them
+        if was_on_ground {
+            if let Some(xy) = self.ground_below(screen, tiles, Float::abs(vel_x) + 1.0) {
+                self.xy = xy;
+            }
+        }
+
         self.vel_x = vel_x;
 
         self.direction = if left { PlayerStandDirection::Left }
@@ -114,9 +168,7 @@ impl PlayerStateStand {
     fn go(&mut self, screen: &Screen, tiles: &Tiles, x_delta: f32, y_delta: f32) -> bool {
         self.xy = self.xy.offset(screen, x_delta, y_delta);
 
-        let direction = into_direction(y_delta < 0.0, y_delta > 0.0, x_delta < 0.0, x_delta > 0.0);
-
-        match tiles.collision_tile(&self.get_rect(), direction) {
+        match tiles.collision_walking(&self.get_rect(), (x_delta, y_delta)) {
             Some((x, y)) => {
                 self.xy = Point::new(screen, (x, y));
                 true
@@ -125,6 +177,12 @@ impl PlayerStateStand {
         }
     }
 
+    /// Where the player would stand if they dropped by up to `distance`, if there's ground that close
+    fn ground_below(&self, screen: &Screen, tiles: &Tiles, distance: f32) -> Option<Point<f32>> {
+        let rect = self.get_rect().offset(screen, 0.0, distance);
+        tiles.collision_walking(&rect, (0.0, distance)).map(|xy| Point::new(screen, xy))
+    }
+
     fn get_rect(&self) -> Rect<f32> {
         Rect::new(self.xy, (16.0, 16.0))
     }
@@ -136,8 +194,8 @@ pub struct PlayerStateDigging {
 }
 
 impl PlayerStateDigging {
-    fn dig(&mut self, screen: &Screen, tiles: &Tiles, up: bool, down: bool, left: bool, right: bool) -> Option<PlayerState> {
-        let speed = 2.0;
+    fn dig(&mut self, screen: &Screen, tiles: &Tiles, physics: &PlayerPhysics, up: bool, down: bool, left: bool, right: bool) -> Option<PlayerState> {
+        let speed = physics.dig_speed;
         let (xy, direction) = if up {
             self.direction = PlayerDiggingDirection::Up;
             (self.xy.offset(screen, 0.0, -speed), Some(into_direction(true, false, false, false)))
@@ -161,7 +219,8 @@ impl PlayerStateDigging {
                 match tiles.collision_tile_digging(&self.get_rect(), direction, up) {
                     Some((x, y, emerge_hit)) => {
                         if emerge_hit {
-                            Some(PlayerState::Emerging(PlayerStateEmerging::new(xy, x - 16.0, y - 16.0)))
+                            let tile_size = tiles.tile_size();
+                            Some(PlayerState::Emerging(PlayerStateEmerging::new(xy, x - tile_size, y - tile_size)))
                         } else {
                             self.xy = Point::new(screen, (x, y));
                             None
@@ -201,7 +260,7 @@ impl PlayerStateEmerging {
         }
     }
 
-    pub fn tick(&mut self, screen: &Screen) -> Option<PlayerState> {
+    pub fn tick(&mut self, screen: &Screen, physics: &PlayerPhysics) -> Option<PlayerState> {
         self.phase += 0.04;
         if self.phase >= 1.0 {
             let direction = if self.to_x < self.from_xy.x() {
@@ -210,19 +269,13 @@ impl PlayerStateEmerging {
                 PlayerStandDirection::Right
             };
 
-            Some(PlayerState::Stand(PlayerStateStand {
-                direction: direction,
-                xy: Point::new(screen, (self.to_x, self.to_y)),
-                vel_x: 0.0,
-                vel_y: 0.0,
-                running_cycle: None
-            }))
+            Some(PlayerState::Stand(PlayerStateStand::new(direction, Point::new(screen, (self.to_x, self.to_y)))))
         } else {
             use std::num::Float;
 
             fn lerp(a: f32, b: f32, p: f32) -> f32 { (b-a)*p + a }
 
-            let coeff = 2.3;
+            let coeff = physics.emerge_arc;
             let y_phase = Float::sin(self.phase * coeff) / Float::sin(coeff);
             let x_phase = self.phase.powf(3.0);
 
@@ -245,8 +298,8 @@ pub struct PlayerStateClimbing {
 }
 
 impl PlayerStateClimbing {
-    fn climb_up(&mut self, screen: &Screen) {
-        let y = self.xy.y() - 2.0;
+    fn climb_up(&mut self, screen: &Screen, physics: &PlayerPhysics) {
+        let y = self.xy.y() - physics.climb_speed;
         self.phase = (self.phase + 0.1) % 1.0;
 
         let ny = if y < self.beanstalk_y { self.beanstalk_y }
@@ -255,8 +308,8 @@ impl PlayerStateClimbing {
         self.xy = self.xy.set_y(screen, ny);
     }
 
-    fn climb_down(&mut self, screen: &Screen) {
-        let y = self.xy.y() + 2.0;
+    fn climb_down(&mut self, screen: &Screen, physics: &PlayerPhysics) {
+        let y = self.xy.y() + physics.climb_speed;
         self.phase = (self.phase + 0.1) % 1.0;
 
         let ny = if y > self.beanstalk_y_max { self.beanstalk_y_max }
@@ -285,48 +338,63 @@ pub enum PlayerState {
     Dying(PlayerStateDying)
 }
 
+/// How many hits the player can take before losing a life
+pub static MAX_HEALTH: u32 = 3;
+pub static STARTING_LIVES: u32 = 3;
+/// How long the player can't be hurt after getting hit or coming back to life
+static INVULNERABLE_STEPS: u32 = 90;
+
 pub struct Player {
     pub state: PlayerState,
-    pub drill: Option<PlayerItemDrill>,
-    pub gun: Option<PlayerItemGun>,
-    pub keys: u32
+    pub inventory: Inventory,
+    /// How far the drill's spinning animation has got
+    pub drill_phase: f32,
+    pub health: u32,
+    /// Including the current one. Losing the last one is game over.
+    pub lives: u32,
+    /// Steps left until the player can be hurt again
+    invulnerable: u32
 }
 
 impl Player {
     pub fn new(pos: Point<f32>) -> Player {
         Player {
             state: Player::get_initial_state(pos),
-            drill: None,
-            gun: None,
-            keys: 0
+            inventory: Inventory::new(),
+            drill_phase: 0.0,
+            health: MAX_HEALTH,
+            lives: STARTING_LIVES,
+            invulnerable: 0
         }
     }
 
+    /// Moves the player to the start of a new level, keeping their items
+    pub fn enter_level(&mut self, pos: Point<f32>) {
+        self.state = Player::get_initial_state(pos);
+    }
+
     fn get_initial_state(xy: Point<f32>) -> PlayerState {
-        PlayerState::Stand(PlayerStateStand {
-            direction: PlayerStandDirection::Left,
-            xy: xy,
-            vel_x: 0.0,
-            vel_y: 0.0,
-            running_cycle: None
-        })
+        PlayerState::Stand(PlayerStateStand::new(PlayerStandDirection::Left, xy))
     }
 
-    pub fn tick(&mut self, screen: &Screen, tiles: &Tiles, up: bool, down: bool, left: bool, right: bool) {
+    /// `jump` is whether the jump button is held, and `new_jump` whether it was just pressed.
+    /// `can_dig` is whether the player is carrying something to dig with.
+    pub fn tick(&mut self, screen: &Screen, tiles: &Tiles, physics: &PlayerPhysics, up: bool, down: bool, left: bool, right: bool, jump: bool, new_jump: bool, can_dig: bool) {
+        let tile_size = tiles.tile_size();
+
         let next_state: Option<PlayerState> = match self.state {
             PlayerState::Stand(ref mut s) => {
-                s.apply_gravity(screen, tiles);
-                s.run(screen, tiles, left, right);
-
-                let has_drill = if let Some(_) = self.drill { true } else { false };
+                s.jump(screen, tiles, physics, new_jump);
+                s.apply_gravity(screen, tiles, physics, jump);
+                s.run(screen, tiles, physics, left, right);
 
-                if has_drill && down {
+                if can_dig && down {
                     match tiles.is_dirt_entrance_below(&s.get_rect()) {
                         Some((x, y)) => {
                             // Dig it up!
                             Some(PlayerState::Digging(PlayerStateDigging {
                                 direction: PlayerDiggingDirection::Down,
-                                xy: Point::new(screen, (x as f32 * 16.0, y as f32 * 16.0))
+                                xy: Point::new(screen, (x as f32 * tile_size, y as f32 * tile_size))
                             }))
                         },
                         None => None
@@ -336,33 +404,33 @@ impl Player {
                 }
             },
             PlayerState::Digging(ref mut s) => {
-                s.dig(screen, tiles, up, down, left, right)
+                s.dig(screen, tiles, physics, up, down, left, right)
             },
             PlayerState::Emerging(ref mut s) => {
-                s.tick(screen)
+                s.tick(screen, physics)
             },
             PlayerState::Climbing(ref mut s) => {
                 if up {
-                    s.climb_up(screen);
+                    s.climb_up(screen, physics);
                     None
                 } else if down {
-                    s.climb_down(screen);
+                    s.climb_down(screen, physics);
                     None
                 } else if left {
                     // Try to jump off
-                    let rect = s.get_rect().offset(screen, -16.0, 0.0);
+                    let rect = s.get_rect().offset(screen, -tile_size, 0.0);
                     match tiles.has_non_blocking_tile(&rect) {
                         Some((x, y)) => {
-                            Some(PlayerState::Emerging(PlayerStateEmerging::new(s.xy, x as f32 * 16.0, y as f32 * 16.0)))
+                            Some(PlayerState::Emerging(PlayerStateEmerging::new(s.xy, x as f32 * tile_size, y as f32 * tile_size)))
                         },
                         None => None
                     }
                 } else if right {
                     // Try to jump off
-                    let rect = s.get_rect().offset(screen, 16.0, 0.0);
+                    let rect = s.get_rect().offset(screen, tile_size, 0.0);
                     match tiles.has_non_blocking_tile(&rect) {
                         Some((x, y)) => {
-                            Some(PlayerState::Emerging(PlayerStateEmerging::new(s.xy, x as f32 * 16.0, y as f32 * 16.0)))
+                            Some(PlayerState::Emerging(PlayerStateEmerging::new(s.xy, x as f32 * tile_size, y as f32 * tile_size)))
                         },
                         None => None
                     }
@@ -373,7 +441,15 @@ impl Player {
             PlayerState::Dying(ref mut s) => {
                 let phase = s.phase + 0.05;
                 if phase >= 1.0 {
-                    Some(Player::get_initial_state(s.regen_coord))
+                    s.phase = 1.0;
+                    if self.lives > 0 {
+                        self.health = MAX_HEALTH;
+                        self.invulnerable = INVULNERABLE_STEPS;
+                        Some(Player::get_initial_state(s.regen_coord))
+                    } else {
+                        // Game over: stay dead
+                        None
+                    }
                 } else {
                     s.phase = phase;
                     None
@@ -381,6 +457,10 @@ impl Player {
             }
         };
 
+        if self.invulnerable > 0 {
+            self.invulnerable -= 1;
+        }
+
         self.tick_item();
 
         match next_state {
@@ -390,9 +470,7 @@ impl Player {
     }
 
     fn tick_item(&mut self) {
-        if let Some(ref mut drill) = self.drill {
-            drill.phase = (drill.phase + 0.1) % 1.0;
-        }
+        self.drill_phase = (self.drill_phase + 0.1) % 1.0;
     }
 
     pub fn get_pos(&self) -> Point<f32> {
@@ -434,21 +512,15 @@ impl Player {
             PlayerState::Emerging(_) => {
                 true
             },
+            PlayerState::Stand(ref s) => {
+                s.is_rising_from_jump()
+            },
             _ => false
         }
     }
 
-    pub fn add_drill(&mut self) {
-        self.drill = Some(PlayerItemDrill {
-            phase: 0.0
-        });
-    }
-
-    pub fn add_gun(&mut self) {
-        self.gun = Some(PlayerItemGun);
-    }
-
-    pub fn try_climb_beanstalk(&mut self, screen: &Screen, beanstalk: &Rect<f32>) {
+    /// Starts climbing a beanstalk or ladder, which covers `beanstalk`
+    pub fn try_climb(&mut self, screen: &Screen, beanstalk: &Rect<f32>) {
         let next_state: Option<PlayerState> = match self.state {
             PlayerState::Stand(ref _s) => {
                 Some(PlayerState::Climbing(PlayerStateClimbing {
@@ -467,7 +539,36 @@ impl Player {
         }
     }
 
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerable > 0
+    }
+
+    /// Whether the player should be left out of this frame, so they flash while invulnerable
+    pub fn is_blinked_out(&self) -> bool {
+        self.invulnerable % 8 >= 4
+    }
+
+    /// Takes away some health, unless the player was hurt too recently.
+    /// The player dies if they're out of health, and comes back at `regen_coord` if they have lives left.
+    /// Returns true if they were hurt.
+    pub fn hurt(&mut self, regen_coord: Point<f32>) -> bool {
+        if !self.is_alive() || self.is_invulnerable() {
+            return false;
+        }
+
+        self.health = self.health.saturating_sub(1);
+        if self.health == 0 {
+            self.die(regen_coord);
+        } else {
+            self.invulnerable = INVULNERABLE_STEPS;
+        }
+        true
+    }
+
+    /// Loses a life
     pub fn die(&mut self, regen_coord: Point<f32>) {
+        self.lives = self.lives.saturating_sub(1);
+        self.invulnerable = 0;
         self.state = PlayerState::Dying(PlayerStateDying {
             xy: self.get_pos(),
             regen_coord: regen_coord,
@@ -475,6 +576,14 @@ impl Player {
         });
     }
 
+    /// Whether the player has died for the last time, and finished dying
+    pub fn is_game_over(&self) -> bool {
+        match self.state {
+            PlayerState::Dying(ref s) => self.lives == 0 && s.phase >= 1.0,
+            _ => false
+        }
+    }
+
     pub fn is_alive(&self) -> bool {
         if let PlayerState::Dying(_) = self.state {
             false
@@ -482,17 +591,4 @@ impl Player {
             true
         }
     }
-
-    pub fn add_keys(&mut self, keys: u32) {
-        self.keys += keys;
-    }
-
-    pub fn try_use_key(&mut self) -> bool {
-        if self.keys > 0 {
-            self.keys -= 1;
-            true
-        } else {
-            false
-        }
-    }
 }
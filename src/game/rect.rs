@@ -1,13 +1,13 @@
 use super::wrapping::Screen;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq)]
 pub struct Rect<S> {
     xy: Point<S>,
     w: S,
     h: S
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq)]
 pub struct Point<S> {
     x: S,
     y: S
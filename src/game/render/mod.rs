@@ -4,6 +4,9 @@ use opengl_util::texture::Texture2D;
 use opengl_util::vertex::VertexArray;
 use game_platforms::GameRenderer;
 use super::{Game, GameStepResult};
+use super::inventory::Ability;
+use super::items::{tile_from_phase, DrawContext};
+use super::level::{ParallaxSource, TileLayer};
 use super::rect::Point;
 
 mod tileset;
@@ -54,6 +57,11 @@ impl<'sdl> GameRenderer<Game<'sdl>, GameStepResult> for Renderer {
             let (w, h) = step_result.viewport;
             gl::Viewport(0, 0, w, h);
             gl::Enable(gl::BLEND);
+
+            if let Some((r, g, b, a)) = game.level.background_color {
+                gl::ClearColor(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, a as f32 / 255.0);
+                gl::Clear(gl::COLOR_BUFFER_BIT);
+            }
         };
 
         let u_projection_view = self.shader_program.get_uniform("projection_view");
@@ -66,9 +74,9 @@ impl<'sdl> GameRenderer<Game<'sdl>, GameStepResult> for Renderer {
             };
             self.tileset.bind(0);
             self.tileset_vao.bind_vao(|vao_ctx| {
-                let tile_size = 16.0;
+                let tile_size = game.level.tile_size;
                 let tileset_drawer = TilesetDrawer {
-                    screen_size: (game.level.width as f32 * tile_size, game.level.height as f32 * tile_size),
+                    screen_size: screen.size(),
                     tile_size: tile_size,
                     draw: |id, model| {
                         uniform.set_mat4(u_model, model);
@@ -80,73 +88,58 @@ impl<'sdl> GameRenderer<Game<'sdl>, GameStepResult> for Renderer {
                     tileset_drawer.draw((x, y), id, flip, rotate_90);
                 };
 
-                uniform.set_mat4(u_projection_view, step_result.projection_view_parallax.as_fixed());
-                // Draw parallax
-                for y in (0..game.level.height) {
-                    for x in (0..game.level.width) {
-                        let pos = Point::new(screen, (x as f32 * tile_size, y as f32 * tile_size));
-                        draw_tile_all(pos, 0x46, (false, false), false);
-                    }
-                }
-
+                let draw_layer = |layer: &TileLayer| {
+                    for (x, y, tile) in layer.iter() {
+                        if let Some(id) = tile.tile_type.atlas_id {
+                            let f = (x as f32 * tile_size, y as f32 * tile_size);
+                            let pos = Point::new(screen, f);
+                            let (flip, rotate_90) = tile.draw_transform();
 
-                uniform.set_mat4(u_projection_view, step_result.projection_view.as_fixed());
-                // Draw all background tiles
-                for (x, y, tile) in game.level.iter() {
-                    if tile.tile_type.id > 0 {
-                        let f = (x as f32 * tile_size, y as f32 * tile_size);
-                        let pos = Point::new(screen, f);
-                        let id = tile.tile_type.id-1;
-                        let flip = (tile.flip_x, tile.flip_y);
-
-                        draw_tile_all(pos, id, flip, false);
+                            draw_tile_all(pos, id, flip, rotate_90);
+                        }
                     }
-                }
-
-                // Draw messages
-                for message in game.items.messages.iter().filter(|m| m.visible) {
-                    for (i, tile_id) in message.tiles.iter().enumerate() {
-                        let offset_x = i as u32 % message.width;
-                        let offset_y = i as u32 / message.width;
-
-                        let xy = message.xy.offset(screen, offset_x as f32 * tile_size, offset_y as f32 * tile_size);
-
-                        draw_tile_all(xy, *tile_id, (false, false), false);
+                };
+                let fill_with_tile = |id: u16, flip: (bool, bool), rotate_90: bool| {
+                    for y in (0..game.level.height) {
+                        for x in (0..game.level.width) {
+                            let pos = Point::new(screen, (x as f32 * tile_size, y as f32 * tile_size));
+                            draw_tile_all(pos, id, flip, rotate_90);
+                        }
                     }
-                }
+                };
 
-                // Draw switches
-                for switch in game.items.switches.iter().filter(|t| t.visible) {
-                    let tile = match switch.is_down {
-                        false => 0x18,
-                        true => 0x19
-                    };
-                    draw_tile_all(switch.xy, tile, (false, false), false);
+                // Draw parallax
+                if game.level.parallax_layers.is_empty() {
+                    uniform.set_mat4(u_projection_view, step_result.projection_view_parallax[0].as_fixed());
+                    fill_with_tile(0x46, (false, false), false);
                 }
-
-                // Draw beanstalks
-                for beanstalk in game.items.beanstalks.iter().filter(|t| t.visible) {
-                    for y in (0..beanstalk.height) {
-                        let tile = match y % 2 {
-                            0 => 0x0E,
-                            _ => 0x0F
-                        };
-                        draw_tile_all(beanstalk.xy.offset(screen, 0.0, y as f32 * tile_size), tile, (false, false), false);
+                for (layer, projection_view) in game.level.parallax_layers.iter().zip(step_result.projection_view_parallax.iter()) {
+                    uniform.set_mat4(u_projection_view, projection_view.as_fixed());
+                    match layer.source {
+                        ParallaxSource::Tile(ref tile) => {
+                            if let Some(id) = tile.tile_type.atlas_id {
+                                let (flip, rotate_90) = tile.draw_transform();
+                                fill_with_tile(id, flip, rotate_90);
+                            }
+                        },
+                        ParallaxSource::Layer(ref tile_layer) => draw_layer(tile_layer)
                     }
                 }
 
-                // Draw chests
-                for chest in game.items.chests.iter().filter(|t| t.visible) {
-                    let tile_offset = match chest.is_static {
-                        true => 0,
-                        false => 5
-                    };
-                    let tile = tile_from_phase(&[0x04, 0x05, 0x06, 0x07, 0x08], chest.phase) + tile_offset;
-                    draw_tile_all(chest.xy.offset(screen, 0.0, tile_size * 3.0/16.0), tile, (false, false), false);
+                uniform.set_mat4(u_projection_view, step_result.projection_view.as_fixed());
+
+                // Draw all background tiles
+                for layer in game.level.layers().iter().filter(|l| !l.foreground) {
+                    draw_layer(layer);
                 }
 
-                // Draw player
-                {
+                let item_drawer = DrawContext::new(screen, tile_size, &draw_tile_all);
+
+                // Draw the items that go behind the player
+                game.items.draw(&item_drawer, false);
+
+                // Draw player, flashing while they can't be hurt
+                if !game.player.is_blinked_out() {
                     use super::player::{PlayerState};
 
                     match game.player.state {
@@ -157,17 +150,19 @@ impl<'sdl> GameRenderer<Game<'sdl>, GameStepResult> for Renderer {
                                 0x00
                             };
                             draw_tile_all(s.xy.offset(screen, 0.0, tile_size * 3.0/16.0), tile, s.direction.get_flip(), false);
-                            if let Some(_) = game.player.gun {
-                                let tile = 0x3B;
+                            let inventory = &game.player.inventory;
+                            let item_types = &game.object_types.items;
+                            let held_tile = inventory.selected().and_then(|id| item_types.get(id).held_tile);
 
+                            if let Some(tile) = held_tile {
                                 let (flip_x, _) = s.direction.get_flip();
                                 let x_offset = match flip_x {
                                     false => 4.0,
                                     true => -4.0
                                 };
                                 draw_tile_all(s.xy.offset(screen, x_offset, tile_size * 5.0/16.0), tile, s.direction.get_flip(), false);
-                            } else if let Some(ref drill) = game.player.drill {
-                                let tile = tile_from_phase(&[0x23, 0x24, 0x25, 0x36], drill.phase);
+                            } else if inventory.has_ability(item_types, Ability::Dig) {
+                                let tile = tile_from_phase(&[0x23, 0x24, 0x25, 0x36], game.player.drill_phase);
 
                                 let (flip_x, _) = s.direction.get_flip();
                                 let x_offset = match flip_x {
@@ -187,9 +182,7 @@ impl<'sdl> GameRenderer<Game<'sdl>, GameStepResult> for Renderer {
                                 Right => (0x38, false)
                             };
 
-                            let drill_tile = if let Some(ref drill) = game.player.drill {
-                                tile_from_phase(&[0x23, 0x24, 0x25, 0x36], drill.phase)
-                            } else { panic!("Player is supposed to have drill!"); };
+                            let drill_tile = tile_from_phase(&[0x23, 0x24, 0x25, 0x36], game.player.drill_phase);
 
                             let (drill_behind, drill_flip, drill_rotate_90, drill_x, drill_y) = match s.direction {
                                 Up => (true, (false, true), true, 0.0, -8.0),
@@ -224,58 +217,39 @@ impl<'sdl> GameRenderer<Game<'sdl>, GameStepResult> for Renderer {
                     };
                 }
 
-                // Draw monsters
-                for monster1 in game.items.monsters1.iter().filter(|m| m.visible ) {
-                    let tile = tile_from_phase(&[0x26, 0x27], monster1.phase);
-                    draw_tile_all(monster1.xy, tile, (false, false), false);
-                }
-
-                for monster2 in game.items.monsters2.iter().filter(|m| m.visible ) {
-                    let tile = tile_from_phase(&[0x28, 0x29], monster2.phase);
-                    draw_tile_all(monster2.xy, tile, (false, false), false);
-                }
-
-                // Draw keys
-                for key in game.items.keys.iter().filter(|k| k.visible) {
-                    let tile = match key.is_sticky {
-                        true => 0x2E,
-                        false => 0x2F
-                    };
-                    draw_tile_all(key.xy, tile, (false, false), false);
-                }
+                // Draw the items that go in front of the player
+                game.items.draw(&item_drawer, true);
 
                 // Draw useless
-                for useless in game.items.useless.iter() {
+                for useless in game.items.effects.useless.iter() {
                     let tile = 0x45;
                     draw_tile_all(useless.xy, tile, (false, false), false);
                 }
 
                 // Draw poofs
-                for poof in game.items.poofs.iter() {
+                for poof in game.items.effects.poofs.iter() {
                     let tile = tile_from_phase(&[0x1A, 0x1B, 0x1C, 0x1D, 0x1E, 0x1F], poof.phase);
                     draw_tile_all(poof.xy, tile, (false, false), false);
                 }
 
                 // Draw bullets
-                for bullet in game.items.bullets.iter() {
+                for bullet in game.items.effects.bullets.iter() {
                     let tile = tile_from_phase(&[0x3C, 0x3D, 0x3E, 0x3F], bullet.phase);
                     let flip_x = bullet.vel_x < 0.0;
                     let offset_x = if flip_x { -16.0 } else { 0.0 };
 
                     draw_tile_all(bullet.xy.offset(screen, offset_x, -8.0), tile, (flip_x, false), false);
                 }
+
+                // Draw foreground tiles over everything else
+                for layer in game.level.layers().iter().filter(|l| l.foreground) {
+                    draw_layer(layer);
+                }
             });
         });
     }
 }
 
-fn tile_from_phase(tiles: &[u16], phase: f32) -> u16 {
-    let i = phase * tiles.len() as f32;
-    let tile_index = if i < 0.0 { 0 } else if i >= tiles.len() as f32 { tiles.len() - 1 } else { i as usize };
-
-    tiles[tile_index]
-}
-
 fn load_default_program() -> Program {
     use opengl_util::shader::{Shader};
 
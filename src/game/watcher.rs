@@ -0,0 +1,67 @@
+use std::path::{Path, PathBuf};
+use super::level::{Level, LevelError, ObjectTypes};
+
+/// Polls a level file and reparses it when its contents change
+pub struct LevelWatcher {
+    path: PathBuf,
+    /// The contents as of the last check. None if the file couldn't be read.
+    contents: Option<String>,
+    poll_steps: u32,
+    countdown: u32
+}
+
+impl LevelWatcher {
+    /// Checks the file once every `poll_steps` calls to `step`
+    pub fn new<P: AsRef<Path>>(path: P, poll_steps: u32) -> LevelWatcher {
+        let path = path.as_ref().to_path_buf();
+        let contents = read_file(&path);
+
+        LevelWatcher {
+            path: path,
+            contents: contents,
+            poll_steps: poll_steps,
+            countdown: poll_steps
+        }
+    }
+
+    pub fn path(&self) -> &Path { &self.path }
+
+    /// Returns the newly parsed level if the file has changed since the last check
+    pub fn step(&mut self, object_types: &ObjectTypes) -> Option<Result<Level, LevelError>> {
+        if self.countdown > 0 {
+            self.countdown -= 1;
+            return None;
+        }
+        self.countdown = self.poll_steps;
+
+        // Files that disappear (e.g. while an editor saves them) are waited out
+        let contents = match read_file(&self.path) {
+            Some(contents) => contents,
+            None => return None
+        };
+
+        if self.contents.as_ref() == Some(&contents) {
+            return None;
+        }
+
+        let level = Level::parse(&self.path, &contents, object_types);
+        self.contents = Some(contents);
+        Some(level)
+    }
+}
+
+fn read_file(path: &Path) -> Option<String> {
+    use std::fs::File;
+    use std::io::Read;
+
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(_) => return None
+    };
+
+    let mut contents = String::new();
+    match file.read_to_string(&mut contents) {
+        Ok(_) => Some(contents),
+        Err(_) => None
+    }
+}
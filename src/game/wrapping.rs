@@ -1,14 +1,16 @@
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq)]
 pub struct Screen {
     pub width: f32,
-    pub height: f32
+    pub height: f32,
+    pub tile_size: f32
 }
 
 impl Screen {
-    pub fn new(width: f32, height: f32) -> Screen {
+    pub fn new(width: f32, height: f32, tile_size: f32) -> Screen {
         Screen {
             width: width,
-            height: height
+            height: height,
+            tile_size: tile_size
         }
     }
 
@@ -1,21 +1,117 @@
 extern crate cgmath;
+extern crate flate2;
 extern crate game_platforms;
 extern crate gl;
 extern crate image;
 extern crate rustc_serialize;
 extern crate sdl2;
 extern crate synth;
+extern crate xml;
 
 mod game;
 #[allow(dead_code)] mod opengl_util;
 mod util;
 
+/// The level that's played when neither `--level` nor `--campaign` is given
+static DEFAULT_LEVEL_PATH: &'static str = "assets/level.tmx";
+
+/// How many window pixels each level pixel takes up
+static WINDOW_SCALE: u32 = 2;
+
+/// The largest the window gets on either side, however big the level is
+static MAX_WINDOW_SIZE: u32 = 4096;
+
+struct Options {
+    campaign: game::campaign::Campaign,
+    /// Where to write the first level as Tiled JSON, instead of playing
+    export_path: Option<String>,
+    /// What to do with object types the game doesn't know
+    unknown_objects: game::level::UnknownObjects,
+    physics: game::physics::PlayerPhysics
+}
+
+fn parse_options() -> Options {
+    use game::campaign::Campaign;
+    use game::level::UnknownObjects;
+    use game::physics::PlayerPhysics;
+
+    let mut args = std::env::args().skip(1);
+    let mut campaign = None;
+    let mut export_path = None;
+    let mut unknown_objects = UnknownObjects::Error;
+    let mut physics = PlayerPhysics::new();
+
+    while let Some(arg) = args.next() {
+        match &arg as &str {
+            "--level" => match args.next() {
+                Some(path) => campaign = Some(Campaign::single(path)),
+                None => println!("--level requires a file name")
+            },
+            "--campaign" => match args.next() {
+                Some(path) => match Campaign::load_from_path(&path) {
+                    Ok(c) => campaign = Some(c),
+                    Err(e) => println!("{}", e)
+                },
+                None => println!("--campaign requires a file name")
+            },
+            "--export" => match args.next() {
+                Some(path) => export_path = Some(path),
+                None => println!("--export requires a file name")
+            },
+            "--unknown-objects" => match args.next().as_ref().and_then(|name| UnknownObjects::from_name(name)) {
+                Some(rule) => unknown_objects = rule,
+                None => println!("--unknown-objects requires one of error, warn or ignore")
+            },
+            "--physics" => match args.next() {
+                Some(path) => match PlayerPhysics::load_from_path(&path) {
+                    Ok(p) => physics = p,
+                    Err(e) => println!("{}", e)
+                },
+                None => println!("--physics requires a file name")
+            },
+            _ => println!("Unknown argument: {}", arg)
+        }
+    }
+
+    Options {
+        campaign: campaign.unwrap_or_else(|| Campaign::single(DEFAULT_LEVEL_PATH)),
+        export_path: export_path,
+        unknown_objects: unknown_objects,
+        physics: physics
+    }
+}
+
+fn export_level(campaign: &game::campaign::Campaign, object_types: &game::level::ObjectTypes, export_path: &str) {
+    use game::level::Level;
+
+    let level = match Level::load_from_path(campaign.current_level(), object_types) {
+        Ok(level) => level,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+
+    match level.save_to_path(export_path) {
+        Ok(()) => println!("Exported {} to {}", campaign.current_level().display(), export_path),
+        Err(e) => println!("{}", e)
+    }
+}
+
 fn main() {
     use game_platforms::sdl2_opengl::{Platform, RenderContext};
 
+    let options = parse_options();
+    let object_types = game::level::ObjectTypes::builtin(options.unknown_objects);
+
+    if let Some(ref export_path) = options.export_path {
+        export_level(&options.campaign, &object_types, export_path);
+        return;
+    }
+
     let sdl_context = sdl2::init(sdl2::INIT_VIDEO).unwrap();
 
-    let game = game::Game::new(&sdl_context);
+    let game = game::Game::new(&sdl_context, options.campaign, object_types, options.physics);
 
     let init_renderer = || {
         gl::load_with(|s: &str| unsafe {
@@ -29,11 +125,7 @@ fn main() {
         game::render::Renderer::new()
     };
 
-    let scale = 2;
-
-    let size = match game.level.level_size_as_u32() {
-        (w, h) => (w as u16 * scale, h as u16 * scale)
-    };
+    let size = window_size(game.level.level_size_as_u32(), WINDOW_SCALE);
 
     let render_ctx = match RenderContext::new("Mr. Scroll", size, (3, 0), init_renderer) {
         Ok(ctx) => ctx,
@@ -45,5 +137,25 @@ fn main() {
         Err(e) => panic!("{}", e)
     };
 
-    let _game = platform.run();
+    match platform.run() {
+        Ok(game) => {
+            if game.is_campaign_complete() {
+                println!("Campaign complete!");
+            } else if game.is_campaign_failed() {
+                println!("The campaign couldn't be finished");
+            }
+        },
+        Err(e) => panic!("{}", e)
+    }
+}
+
+/// The window size for a level, scaled up, but no bigger than `MAX_WINDOW_SIZE`
+fn window_size((width, height): (u32, u32), scale: u32) -> (u16, u16) {
+    use std::cmp::{min, max};
+
+    let side = |length: u32| {
+        let scaled = length.checked_mul(scale).unwrap_or(MAX_WINDOW_SIZE);
+        max(min(scaled, MAX_WINDOW_SIZE), 1) as u16
+    };
+    (side(width), side(height))
 }